@@ -0,0 +1,191 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use lsp_server::{ErrorCode, Message, Notification, RequestId, Response};
+use lsp_types::{
+    notification::{Cancel, Notification as _},
+    CancelParams, NumberOrString,
+};
+
+/// Requests whose answer is only useful for the latest cursor position. A
+/// newer request of the same kind makes the older ones obsolete.
+const SUPERSEDABLE_METHODS: &[&str] = &["textDocument/completion", "textDocument/hover"];
+
+/// Why a request is answered without its result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cancellation {
+    /// The client sent a `$/cancelRequest`
+    Client,
+    /// A newer request of the same kind made the result obsolete
+    Superseded,
+}
+
+#[derive(Debug)]
+struct InFlightRequest {
+    method: String,
+    cancelled: Option<Cancellation>,
+}
+
+/// Requests that have been received but not answered yet, along with whether
+/// they were cancelled in the meantime
+type InFlight = HashMap<RequestId, InFlightRequest>;
+
+static IN_FLIGHT_REQUESTS: OnceLock<Arc<Mutex<InFlight>>> = OnceLock::new();
+
+pub fn init_in_flight_requests() {
+    _ = IN_FLIGHT_REQUESTS.set(Arc::new(Mutex::new(HashMap::new())));
+}
+
+fn lock_in_flight() -> std::sync::MutexGuard<'static, InFlight> {
+    IN_FLIGHT_REQUESTS
+        .get()
+        .expect("in flight requests not initialized")
+        .lock()
+        .expect("in flight requests mutex poisoned")
+}
+
+fn cancelled_id(noti: &Notification) -> Option<RequestId> {
+    if noti.method != Cancel::METHOD {
+        return None;
+    }
+
+    let params: CancelParams = serde_json::from_value(noti.params.clone()).ok()?;
+    Some(match params.id {
+        NumberOrString::Number(id) => id.into(),
+        NumberOrString::String(id) => id.into(),
+    })
+}
+
+/// Records every request as in flight and flags the ones a `$/cancelRequest`
+/// refers to, or that a newer completion or hover request supersedes.
+/// Cancellations for requests that were already answered are dropped, so the
+/// table never outgrows the requests being worked on.
+pub fn track_message(msg: &Message) {
+    match msg {
+        Message::Request(req) => {
+            let mut in_flight = lock_in_flight();
+            if SUPERSEDABLE_METHODS.contains(&req.method.as_str()) {
+                in_flight
+                    .values_mut()
+                    .filter(|request| request.method == req.method)
+                    .for_each(|request| {
+                        request.cancelled.get_or_insert(Cancellation::Superseded);
+                    });
+            }
+
+            in_flight.insert(
+                req.id.clone(),
+                InFlightRequest {
+                    method: req.method.clone(),
+                    cancelled: None,
+                },
+            );
+        }
+        Message::Notification(noti) => {
+            if let Some(id) = cancelled_id(noti) {
+                if let Some(request) = lock_in_flight().get_mut(&id) {
+                    request.cancelled = Some(Cancellation::Client);
+                }
+            }
+        }
+        Message::Response(_) => {}
+    }
+}
+
+pub fn is_cancelled(id: &RequestId) -> bool {
+    lock_in_flight()
+        .get(id)
+        .is_some_and(|request| request.cancelled.is_some())
+}
+
+/// Stops tracking `id` once it is answered, returning why it was cancelled
+pub fn finish_request(id: &RequestId) -> Option<Cancellation> {
+    lock_in_flight().remove(id)?.cancelled
+}
+
+/// The error for a request the client cancelled, or `ContentModified` for one
+/// a newer request superseded, which clients drop without reporting
+pub fn cancelled_response(id: RequestId, cancellation: Cancellation) -> Response {
+    let (code, message) = match cancellation {
+        Cancellation::Client => (ErrorCode::RequestCanceled, "request cancelled"),
+        Cancellation::Superseded => (ErrorCode::ContentModified, "request superseded"),
+    };
+
+    Response::new_err(id, code as i32, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_server::{Message, Notification, Request, RequestId};
+
+    use super::{
+        finish_request, init_in_flight_requests, is_cancelled, track_message, Cancellation,
+    };
+
+    fn cancel(id: i32) -> Message {
+        Message::Notification(Notification {
+            method: "$/cancelRequest".to_string(),
+            params: serde_json::json!({ "id": id }),
+        })
+    }
+
+    fn request(id: i32) -> Message {
+        request_for(id, "textDocument/definition")
+    }
+
+    fn request_for(id: i32, method: &str) -> Message {
+        Message::Request(Request {
+            id: id.into(),
+            method: method.to_string(),
+            params: serde_json::Value::Null,
+        })
+    }
+
+    #[test]
+    fn test_it_flags_cancelled_requests_until_they_finish() {
+        init_in_flight_requests();
+
+        track_message(&request(7001));
+        track_message(&request(7002));
+        track_message(&cancel(7001));
+
+        assert!(is_cancelled(&RequestId::from(7001)));
+        assert!(!is_cancelled(&RequestId::from(7002)));
+
+        assert_eq!(
+            finish_request(&RequestId::from(7001)),
+            Some(Cancellation::Client)
+        );
+        assert_eq!(finish_request(&RequestId::from(7002)), None);
+        assert!(!is_cancelled(&RequestId::from(7001)));
+    }
+
+    #[test]
+    fn test_it_ignores_cancellations_of_answered_requests() {
+        init_in_flight_requests();
+
+        track_message(&cancel(7003));
+        track_message(&request(7003));
+
+        assert!(!is_cancelled(&RequestId::from(7003)));
+        finish_request(&RequestId::from(7003));
+    }
+
+    #[test]
+    fn test_it_supersedes_older_completions() {
+        init_in_flight_requests();
+
+        track_message(&request_for(7004, "textDocument/completion"));
+        track_message(&request(7005));
+        track_message(&request_for(7006, "textDocument/completion"));
+
+        assert_eq!(
+            finish_request(&RequestId::from(7004)),
+            Some(Cancellation::Superseded)
+        );
+        assert_eq!(finish_request(&RequestId::from(7005)), None);
+        assert_eq!(finish_request(&RequestId::from(7006)), None);
+    }
+}
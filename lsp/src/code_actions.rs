@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, NumberOrString, Range, TextEdit,
+    Url, WorkspaceEdit,
+};
+use tree_sitter::{Node, Point};
+
+use crate::{
+    custom_data::custom_data,
+    diagnostics::{
+        closest_match, extra_swap_styles, is_duplicate_attribute, is_known_attribute,
+        suggest_attribute, DUPLICATE_ATTRIBUTE, INVALID_BOOST, INVALID_SWAP, LEGACY_HX_ON,
+        UNKNOWN_ATTRIBUTE,
+    },
+    htmx::{
+        hx_on::migrate_legacy_hx_on,
+        prefix::with_prefix,
+        syntax::{tokenize, Token},
+        version::{available_items, htmx_version},
+        HX_ATTRIBUTE_VALUES,
+    },
+    text_store::Document,
+    tree_sitter::{point_to_lsp_position, range_to_lsp_range},
+    tree_sitter_querier::{query_htmx_attributes, HtmxAttribute},
+};
+
+/// Attributes whose value, or for hx-swap its first word, must be one of the
+/// values in HX_ATTRIBUTE_VALUES, along with the code of the diagnostic
+/// reporting other values
+const ENUMERATED_ATTRIBUTES: &[(&str, &str)] =
+    &[("hx-swap", INVALID_SWAP), ("hx-boost", INVALID_BOOST)];
+
+fn overlaps(a: &Range, b: &Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+fn has_code(diagnostic: &Diagnostic, code: &str) -> bool {
+    diagnostic.code == Some(NumberOrString::String(code.to_string()))
+}
+
+/// The diagnostics with `code` reported on `range`, which a fix resolves
+fn fixed_diagnostics(diagnostics: &[Diagnostic], code: &str, range: &Range) -> Vec<Diagnostic> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| has_code(diagnostic, code) && overlaps(&diagnostic.range, range))
+        .cloned()
+        .collect()
+}
+
+fn quick_fix(
+    uri: &Url,
+    title: String,
+    edits: Vec<TextEdit>,
+    diagnostics: Vec<Diagnostic>,
+) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(diagnostics),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..Default::default()
+        }),
+        is_preferred: Some(true),
+        ..Default::default()
+    })
+}
+
+/// The range of a whole attribute, from its name to its closing quote, along
+/// with the quote it uses
+fn attribute_range(text: &str, attribute: &HtmxAttribute) -> Option<(Range, char)> {
+    let value = attribute.value.as_ref()?;
+
+    let quote = text[..value.range.start_byte].chars().next_back();
+    let (quote, end) = match quote {
+        Some(quote @ ('"' | '\'')) => (
+            quote,
+            Point::new(value.range.end_point.row, value.range.end_point.column + 1),
+        ),
+        _ => ('"', value.range.end_point),
+    };
+
+    Some((
+        Range::new(
+            point_to_lsp_position(text, attribute.name_range.start_point),
+            point_to_lsp_position(text, end),
+        ),
+        quote,
+    ))
+}
+
+/// Rewrites a legacy `hx-on="event: code"` attribute into `hx-on:<event>`
+/// attributes. An attribute on a line of its own is replaced by one line per
+/// event, indented the same way.
+fn migrate_hx_on(
+    uri: &Url,
+    text: &str,
+    attribute: &HtmxAttribute,
+    diagnostics: &[Diagnostic],
+) -> Option<CodeActionOrCommand> {
+    let (range, quote) = attribute_range(text, attribute)?;
+
+    let line_start = text[..attribute.name_range.start_byte]
+        .rfind('\n')
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    let before = &text[line_start..attribute.name_range.start_byte];
+    let separator = match before.trim().is_empty() {
+        true => format!("\n{before}"),
+        false => " ".to_string(),
+    };
+
+    let new_text = migrate_legacy_hx_on(
+        &attribute.prefix,
+        &attribute.value.as_ref()?.value,
+        quote,
+        &separator,
+    )?;
+
+    Some(quick_fix(
+        uri,
+        format!("Rewrite as `{}on:<event>` attributes", attribute.prefix),
+        vec![TextEdit { range, new_text }],
+        fixed_diagnostics(diagnostics, LEGACY_HX_ON, &range),
+    ))
+}
+
+/// Replaces an unknown attribute name with the known one it is closest to
+fn rename_unknown_attribute(
+    uri: &Url,
+    text: &str,
+    attribute: &HtmxAttribute,
+    custom_attributes: &[String],
+    diagnostics: &[Diagnostic],
+) -> Option<CodeActionOrCommand> {
+    if is_known_attribute(&attribute.name, custom_attributes) {
+        return None;
+    }
+
+    let suggestion = with_prefix(
+        &attribute.prefix,
+        &suggest_attribute(&attribute.name, custom_attributes)?,
+    );
+    let range = range_to_lsp_range(text, attribute.name_range);
+
+    Some(quick_fix(
+        uri,
+        format!("Change to `{suggestion}`"),
+        vec![TextEdit {
+            range,
+            new_text: suggestion,
+        }],
+        fixed_diagnostics(diagnostics, UNKNOWN_ATTRIBUTE, &range),
+    ))
+}
+
+/// Replaces a value that is reported as not one an attribute accepts with the
+/// accepted value it is closest to
+fn fix_enumerated_value(
+    uri: &Url,
+    text: &str,
+    root: Node<'_>,
+    attribute: &HtmxAttribute,
+    diagnostics: &[Diagnostic],
+) -> Option<CodeActionOrCommand> {
+    let name = attribute.name.as_str();
+    let (_, code) = ENUMERATED_ATTRIBUTES
+        .iter()
+        .find(|(attribute, _)| *attribute == name)?;
+
+    let value = attribute.value.as_ref()?;
+    let token = match name {
+        // Modifiers follow the swap style and always contain a colon
+        "hx-swap" => tokenize(&value.value)
+            .into_iter()
+            .next()
+            .filter(|token| !token.text.contains(':'))?,
+        _ => Token {
+            text: &value.value,
+            start: 0,
+        },
+    };
+
+    // Only values the diagnostics reject, which know about the styles
+    // extensions and the custom data file add
+    let range = range_to_lsp_range(text, value.sub_range(token.range()));
+    let fixed = fixed_diagnostics(diagnostics, code, &range);
+    if fixed.is_empty() {
+        return None;
+    }
+
+    let items = available_items(
+        Some(name),
+        HX_ATTRIBUTE_VALUES.get(name)?,
+        htmx_version(Some(uri.as_str())),
+    );
+    let extra = match name {
+        "hx-swap" => extra_swap_styles(root, text, attribute),
+        _ => vec![],
+    };
+    let values: Vec<&str> = items
+        .iter()
+        .map(|item| item.name.as_ref())
+        .chain(extra.iter().map(String::as_str))
+        .filter(|value| !value.contains('*'))
+        .collect();
+
+    let suggestion = values
+        .iter()
+        .find(|value| value.eq_ignore_ascii_case(token.text))
+        .copied()
+        .or_else(|| closest_match(token.text, values.iter().copied()))?
+        .to_string();
+
+    Some(quick_fix(
+        uri,
+        format!("Change to `{suggestion}`"),
+        vec![TextEdit {
+            range,
+            new_text: suggestion,
+        }],
+        fixed,
+    ))
+}
+
+/// Removes an attribute repeating one written earlier on the same tag, along
+/// with the whitespace in front of it. Browsers keep the first of duplicated
+/// attributes, so this doesn't change what the page does.
+fn remove_duplicate_attribute(
+    uri: &Url,
+    text: &str,
+    root: Node<'_>,
+    attribute: &HtmxAttribute,
+    attributes: &[HtmxAttribute],
+    diagnostics: &[Diagnostic],
+) -> Option<CodeActionOrCommand> {
+    if !is_duplicate_attribute(root, attributes, attribute) {
+        return None;
+    }
+
+    let node = attribute.node(root)?;
+    let start = node.prev_sibling().unwrap_or(node).end_position();
+    let range = Range::new(
+        point_to_lsp_position(text, start),
+        point_to_lsp_position(text, node.end_position()),
+    );
+
+    Some(quick_fix(
+        uri,
+        format!("Remove duplicate `{}`", attribute.written_name()),
+        vec![TextEdit {
+            range,
+            new_text: String::new(),
+        }],
+        fixed_diagnostics(
+            diagnostics,
+            DUPLICATE_ATTRIBUTE,
+            &range_to_lsp_range(text, attribute.name_range),
+        ),
+    ))
+}
+
+/// The fixes available for the attributes in `range`
+pub fn code_actions(
+    uri: &Url,
+    document: &Document,
+    range: Range,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeActionOrCommand> {
+    let text = document.text.as_str();
+    let root = document.tree.root_node();
+    let custom_attributes: Vec<_> = custom_data()
+        .attributes
+        .iter()
+        .map(|attribute| attribute.name.clone())
+        .collect();
+
+    let attributes = query_htmx_attributes(root, text);
+    let in_range = |attribute: &&HtmxAttribute| {
+        attribute
+            .node(root)
+            .is_some_and(|node| overlaps(&range_to_lsp_range(text, node.range()), &range))
+    };
+
+    attributes
+        .iter()
+        .filter(in_range)
+        .flat_map(|attribute| {
+            let migrate = match attribute.name.as_str() {
+                "hx-on" => migrate_hx_on(uri, text, attribute, diagnostics),
+                _ => None,
+            };
+
+            [
+                migrate,
+                rename_unknown_attribute(uri, text, attribute, &custom_attributes, diagnostics),
+                fix_enumerated_value(uri, text, root, attribute, diagnostics),
+                remove_duplicate_attribute(uri, text, root, attribute, &attributes, diagnostics),
+            ]
+        })
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{CodeActionOrCommand, Position, Range, Url};
+
+    use super::code_actions;
+    use crate::{
+        config::DiagnosticsConfig, diagnostics::document_diagnostics, text_store::Document,
+    };
+
+    fn edits(text: &str, position: Position) -> Vec<String> {
+        let uri = Url::parse("file:///actions.html").expect("valid uri");
+        let document = Document::new(text.to_string());
+        let diagnostics = document_diagnostics(&document, &DiagnosticsConfig::default(), None);
+
+        code_actions(
+            &uri,
+            &document,
+            Range::new(position, position),
+            &diagnostics,
+        )
+        .into_iter()
+        .flat_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => action.edit?.changes?.remove(&uri),
+            CodeActionOrCommand::Command(_) => None,
+        })
+        .flatten()
+        .map(|edit| edit.new_text)
+        .collect()
+    }
+
+    #[test]
+    fn test_it_migrates_legacy_hx_on_keeping_quotes_and_indentation() {
+        let text = "<button hx-get=\"/a\"\n        hx-on='htmx:beforeRequest: start()\n               htmx:afterRequest: stop()'>\n</button>";
+
+        assert_eq!(
+            edits(text, Position::new(1, 10)),
+            vec!["hx-on::before-request='start()'\n        hx-on::after-request='stop()'"]
+        );
+
+        let inline = r#"<button data-hx-on="click: go()">"#;
+        assert_eq!(
+            edits(inline, Position::new(0, 10)),
+            vec![r#"data-hx-on:click="go()""#]
+        );
+    }
+
+    #[test]
+    fn test_it_fixes_misspelled_names_and_values() {
+        let text =
+            r##"<div data-hx-tagret="#a" hx-swap="outerHtml swap:1s" hx-boost="ture"></div>"##;
+
+        assert_eq!(edits(text, Position::new(0, 8)), vec!["data-hx-target"]);
+        assert_eq!(edits(text, Position::new(0, 36)), vec!["outerHTML"]);
+        assert_eq!(edits(text, Position::new(0, 62)), vec!["true"]);
+        assert!(edits(r#"<div hx-swap="innerHTML"></div>"#, Position::new(0, 8)).is_empty());
+    }
+
+    #[test]
+    fn test_it_keeps_swap_styles_of_enabled_extensions() {
+        let text = r#"<div hx-ext="morph"><p hx-swap="morph"></p></div>"#;
+        assert!(edits(text, Position::new(0, 34)).is_empty());
+
+        let text = r#"<div hx-ext="morph"><p hx-swap="morp"></p></div>"#;
+        assert_eq!(edits(text, Position::new(0, 34)), vec!["morph"]);
+    }
+
+    #[test]
+    fn test_it_removes_duplicated_attributes() {
+        let text = r##"<div hx-target="#a" hx-get="/a"
+     hx-target="#b"></div><p hx-target="#c"></p>"##;
+        let uri = Url::parse("file:///actions.html").expect("valid uri");
+        let document = Document::new(text.to_string());
+        let whole = Range::new(Position::new(0, 0), Position::new(1, 40));
+
+        let diagnostics = document_diagnostics(&document, &DiagnosticsConfig::default(), None);
+
+        let removals: Vec<_> = code_actions(&uri, &document, whole, &diagnostics)
+            .into_iter()
+            .flat_map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => Some((
+                    action.edit?.changes?.remove(&uri)?[0].range,
+                    action.diagnostics?.len(),
+                )),
+                CodeActionOrCommand::Command(_) => None,
+            })
+            .collect();
+
+        // The fix resolves the diagnostic reported on the repeated attribute
+        assert_eq!(
+            removals,
+            vec![(Range::new(Position::new(0, 31), Position::new(1, 19)), 1)]
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
+
+use log::{error, info, warn};
+use lsp_server::{Request, RequestId, Response};
+use lsp_types::{
+    request::{Request as _, WorkspaceConfiguration},
+    ConfigurationItem, ConfigurationParams, InitializeParams,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{Map, Value};
+
+use crate::htmx::prefix::set_attribute_prefixes;
+
+/// The section clients keep our settings under, e.g. `"htmx": { ... }` in
+/// `settings.json`
+pub const CONFIGURATION_SECTION: &str = "htmx";
+
+/// The keys of our section, one per field of Config
+const SETTINGS: &[&str] = &[
+    "diagnostics",
+    "htmxVersion",
+    "attributePrefixes",
+    "extensions",
+    "templateGlobs",
+    "patterns",
+];
+
+/// Which major version of htmx a project uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub enum HtmxVersion {
+    #[serde(rename = "1", alias = "1.x")]
+    V1,
+    #[serde(rename = "2", alias = "2.x")]
+    V2,
+}
+
+impl HtmxVersion {
+    pub fn from_major(major: char) -> Option<HtmxVersion> {
+        match major {
+            '1' => Some(HtmxVersion::V1),
+            '2' => Some(HtmxVersion::V2),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for HtmxVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HtmxVersion::V1 => write!(f, "htmx 1"),
+            HtmxVersion::V2 => write!(f, "htmx 2"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DiagnosticsConfig {
+    /// Turns every diagnostic off at once
+    pub enable: bool,
+    /// Diagnostic codes mapped to whether they are reported, rules that are
+    /// not listed stay enabled
+    pub rules: HashMap<String, bool>,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        DiagnosticsConfig {
+            enable: true,
+            rules: HashMap::new(),
+        }
+    }
+}
+
+impl DiagnosticsConfig {
+    pub fn is_rule_enabled(&self, code: &str) -> bool {
+        self.enable && self.rules.get(code).copied().unwrap_or(true)
+    }
+}
+
+/// An htmx extension that is not part of the built in catalog
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExtensionDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Swap styles the extension adds to hx-swap, a trailing `:*` accepts
+    /// any value after the colon
+    #[serde(default, rename = "swapStyles")]
+    pub swap_styles: Vec<String>,
+}
+
+/// A recipe of several attributes offered in completion, written in snippet
+/// syntax with `hx-` attributes
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PatternDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub diagnostics: DiagnosticsConfig,
+    /// Pins the htmx version instead of detecting it from the project
+    pub htmx_version: Option<HtmxVersion>,
+    /// Prefixes besides `hx-` that htmx attributes are written with
+    pub attribute_prefixes: Vec<String>,
+    /// Extensions offered in `hx-ext` on top of the built in ones
+    pub extensions: Vec<ExtensionDefinition>,
+    /// Globs of files to index on top of the known template extensions
+    pub template_globs: Vec<String>,
+    /// Patterns offered in completion on top of the built in ones
+    pub patterns: Vec<PatternDefinition>,
+}
+
+/// Replaces `target` with the setting under `key`, if there is one. A setting
+/// that fails to parse is logged and leaves `target` as it was.
+fn read_setting<T: DeserializeOwned>(settings: &Map<String, Value>, key: &str, target: &mut T) {
+    let value = match settings.get(key) {
+        Some(value) => value,
+        None => return,
+    };
+
+    match T::deserialize(value) {
+        Ok(setting) => *target = setting,
+        Err(err) => error!("invalid setting `{key}` {value}: {err}"),
+    }
+}
+
+impl Config {
+    /// Reads every setting on its own, so one invalid setting doesn't throw
+    /// away the others
+    pub fn from_settings(settings: &Map<String, Value>) -> Config {
+        let mut config = Config::default();
+
+        read_setting(settings, "diagnostics", &mut config.diagnostics);
+        read_setting(settings, "htmxVersion", &mut config.htmx_version);
+        read_setting(
+            settings,
+            "attributePrefixes",
+            &mut config.attribute_prefixes,
+        );
+        read_setting(settings, "extensions", &mut config.extensions);
+        read_setting(settings, "templateGlobs", &mut config.template_globs);
+        read_setting(settings, "patterns", &mut config.patterns);
+
+        for key in settings
+            .keys()
+            .filter(|key| !SETTINGS.contains(&key.as_str()))
+        {
+            warn!("unknown setting `{key}`");
+        }
+
+        config
+    }
+
+    /// Reads the settings either from our section or, when the client sends
+    /// them unwrapped, from the value itself. Null or empty settings carry
+    /// nothing, clients that send them expect to be asked with a
+    /// `workspace/configuration` request instead.
+    pub fn from_value(value: &Value) -> Option<Config> {
+        let value = value.get(CONFIGURATION_SECTION).unwrap_or(value);
+
+        match value.as_object() {
+            Some(settings) if !settings.is_empty() => Some(Config::from_settings(settings)),
+            _ => None,
+        }
+    }
+
+    /// Reads the settings from a `workspace/configuration` response, where a
+    /// null section means the user has not configured anything
+    pub fn from_response(response: &Response) -> Option<Config> {
+        if let Some(err) = &response.error {
+            error!("workspace/configuration failed: {:?}", err);
+            return None;
+        }
+
+        match response.result.as_ref()?.as_array()?.first() {
+            Some(Value::Null) | None => Some(Config::default()),
+            Some(Value::Object(settings)) => Some(Config::from_settings(settings)),
+            Some(section) => {
+                error!("invalid configuration {section}");
+                None
+            }
+        }
+    }
+}
+
+/// The current settings, swapped for a new Arc when they change so readers
+/// share them without copying
+pub static CONFIG: OnceLock<Mutex<Arc<Config>>> = OnceLock::new();
+
+/// Whether the client answers `workspace/configuration` requests
+static PULL_CONFIGURATION: OnceLock<bool> = OnceLock::new();
+
+/// The id of the `workspace/configuration` request awaiting an answer
+static PENDING_REQUEST: Mutex<Option<RequestId>> = Mutex::new(None);
+
+static NEXT_REQUEST_ID: AtomicU32 = AtomicU32::new(0);
+
+pub fn init_config(params: &InitializeParams) {
+    let config = params
+        .initialization_options
+        .as_ref()
+        .and_then(Config::from_value)
+        .unwrap_or_default();
+
+    info!("initial configuration {:?}", config);
+    set_attribute_prefixes(&config.attribute_prefixes);
+    _ = CONFIG.set(Mutex::new(Arc::new(config)));
+
+    let pull = params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.configuration)
+        .unwrap_or(false);
+    _ = PULL_CONFIGURATION.set(pull);
+}
+
+/// A snapshot of the current settings
+pub fn config() -> Arc<Config> {
+    CONFIG
+        .get()
+        .map(|config| config.lock().expect("config mutex poisoned").clone())
+        .unwrap_or_default()
+}
+
+pub fn set_config(config: Config) {
+    info!("configuration changed to {:?}", config);
+    set_attribute_prefixes(&config.attribute_prefixes);
+    let config = Arc::new(config);
+    match CONFIG.get() {
+        Some(current) => *current.lock().expect("config mutex poisoned") = config,
+        None => _ = CONFIG.set(Mutex::new(config)),
+    }
+}
+
+/// Asks the client for our settings section, if it supports being asked
+pub fn configuration_request() -> Option<Request> {
+    if !PULL_CONFIGURATION.get().copied().unwrap_or(false) {
+        return None;
+    }
+
+    let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::SeqCst);
+    let id = RequestId::from(format!("htmx-lsp/configuration/{id}"));
+
+    *PENDING_REQUEST.lock().expect("config mutex poisoned") = Some(id.clone());
+
+    Some(Request::new(
+        id,
+        WorkspaceConfiguration::METHOD.to_string(),
+        ConfigurationParams {
+            items: vec![ConfigurationItem {
+                scope_uri: None,
+                section: Some(CONFIGURATION_SECTION.to_string()),
+            }],
+        },
+    ))
+}
+
+/// Whether `response` answers our latest `workspace/configuration` request.
+/// Answers to requests that a newer one replaced are stale.
+pub fn is_configuration_response(response: &Response) -> bool {
+    let mut pending = PENDING_REQUEST.lock().expect("config mutex poisoned");
+    if pending.as_ref() != Some(&response.id) {
+        return false;
+    }
+
+    *pending = None;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, HtmxVersion};
+
+    #[test]
+    fn test_it_parses_settings_with_or_without_section() {
+        let settings = serde_json::json!({
+            "diagnostics": { "rules": { "unknown-id": false } },
+            "htmxVersion": "2",
+            "attributePrefixes": ["x-hx-"],
+            "extensions": [{ "name": "my-ext", "description": "Does things" }],
+            "templateGlobs": ["templates/**/*.txt"],
+            "patterns": [{ "name": "modal", "body": "hx-get=\"$1\" hx-target=\"#modal\"" }],
+        });
+
+        let config = Config::from_value(&settings).expect("valid settings");
+        assert_eq!(
+            Config::from_value(&serde_json::json!({ "htmx": settings })),
+            Some(config.clone())
+        );
+
+        assert_eq!(config.htmx_version, Some(HtmxVersion::V2));
+        assert_eq!(config.attribute_prefixes, vec!["x-hx-"]);
+        assert_eq!(config.extensions[0].name, "my-ext");
+        assert_eq!(config.template_globs, vec!["templates/**/*.txt"]);
+        assert_eq!(config.patterns[0].name, "modal");
+        assert!(!config.diagnostics.is_rule_enabled("unknown-id"));
+        assert!(config.diagnostics.is_rule_enabled("invalid-swap"));
+    }
+
+    #[test]
+    fn test_it_keeps_defaults_for_missing_or_invalid_settings() {
+        let invalid = Config::from_value(&serde_json::json!({
+            "htmxVersion": 3,
+            "attributePrefixes": ["x-hx-"],
+        }))
+        .expect("settings");
+        assert_eq!(invalid.htmx_version, None);
+        assert_eq!(invalid.attribute_prefixes, vec!["x-hx-"]);
+
+        let disabled = Config::from_value(&serde_json::json!({
+            "diagnostics": { "enable": false }
+        }))
+        .expect("valid settings");
+        assert!(!disabled.diagnostics.is_rule_enabled("invalid-swap"));
+    }
+
+    #[test]
+    fn test_it_asks_for_settings_when_none_are_sent() {
+        assert_eq!(Config::from_value(&serde_json::json!({})), None);
+        assert_eq!(Config::from_value(&serde_json::json!({ "htmx": {} })), None);
+        assert_eq!(
+            Config::from_value(&serde_json::json!({ "htmx": null })),
+            None
+        );
+        assert_eq!(Config::from_value(&serde_json::Value::Null), None);
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+};
+
+use log::{error, info, warn};
+use lsp_server::{Request, RequestId, Response};
+use lsp_types::{
+    notification::{DidChangeWatchedFiles, Notification as _},
+    request::{RegisterCapability, Request as _},
+    DidChangeWatchedFilesRegistrationOptions, FileSystemWatcher, GlobPattern, InitializeParams,
+    Registration, RegistrationParams, Url,
+};
+use serde::Deserialize;
+
+use crate::{config::ExtensionDefinition, htmx::prefix::CANONICAL_PREFIX};
+
+/// The file in a workspace folder that describes a project's own attributes
+/// and extensions
+pub const CUSTOM_DATA_FILE: &str = "htmx-lsp.json";
+
+const WATCH_REQUEST_ID: &str = "htmx-lsp/watch-custom-data";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CustomValue {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CustomAttribute {
+    /// The canonical `hx-` name, the other prefixes are accepted for it as well
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Values offered in completion, leave empty for free form values
+    #[serde(default)]
+    pub values: Vec<CustomValue>,
+}
+
+/// Attributes and extensions a project defines on top of the built in catalog,
+/// similar to the custom data files of VS Code's HTML support
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CustomData {
+    pub attributes: Vec<CustomAttribute>,
+    pub extensions: Vec<ExtensionDefinition>,
+}
+
+impl CustomData {
+    pub fn parse(text: &str) -> Result<CustomData, serde_json::Error> {
+        let mut data: CustomData = serde_json::from_str(text)?;
+
+        data.attributes.retain(|attribute| {
+            let canonical = attribute.name.starts_with(CANONICAL_PREFIX);
+            if !canonical {
+                warn!(
+                    "ignoring custom attribute {}, names must start with {CANONICAL_PREFIX}",
+                    attribute.name
+                );
+            }
+            canonical
+        });
+
+        Ok(data)
+    }
+
+    fn extend(&mut self, other: CustomData) {
+        self.attributes.extend(other.attributes);
+        self.extensions.extend(other.extensions);
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&CustomAttribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+}
+
+fn lock_custom_data() -> MutexGuard<'static, Arc<CustomData>> {
+    static CUSTOM_DATA: OnceLock<Mutex<Arc<CustomData>>> = OnceLock::new();
+
+    CUSTOM_DATA
+        .get_or_init(|| Mutex::new(Arc::default()))
+        .lock()
+        .expect("custom data mutex poisoned")
+}
+
+/// A snapshot of the definitions loaded from every workspace folder, replaced
+/// as a whole when they are loaded again
+pub fn custom_data() -> Arc<CustomData> {
+    lock_custom_data().clone()
+}
+
+fn read_custom_data(path: &Path) -> Option<CustomData> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(err) => {
+            error!("failed to read {:?}: {:?}", path, err);
+            return None;
+        }
+    };
+
+    match CustomData::parse(&text) {
+        Ok(data) => Some(data),
+        Err(err) => {
+            error!("invalid custom data in {:?}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Reads the custom data file of every workspace folder again, replacing what
+/// was loaded before
+pub fn load_custom_data(roots: &[impl AsRef<Path>]) {
+    let mut data = CustomData::default();
+
+    for root in roots {
+        let path = root.as_ref().join(CUSTOM_DATA_FILE);
+        if let Some(custom) = read_custom_data(&path) {
+            info!(
+                "loaded {} custom attributes and {} extensions from {:?}",
+                custom.attributes.len(),
+                custom.extensions.len(),
+                path
+            );
+            data.extend(custom);
+        }
+    }
+
+    *lock_custom_data() = Arc::new(data);
+}
+
+/// Asks the client to tell us about changes to the custom data files, which
+/// are rarely open in the editor when they change
+pub fn watch_request(params: &InitializeParams) -> Option<Request> {
+    let dynamic = params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.did_change_watched_files)
+        .and_then(|watched| watched.dynamic_registration)
+        .unwrap_or(false);
+    if !dynamic {
+        return None;
+    }
+
+    let options = DidChangeWatchedFilesRegistrationOptions {
+        watchers: vec![FileSystemWatcher {
+            glob_pattern: GlobPattern::String(format!("**/{CUSTOM_DATA_FILE}")),
+            kind: None,
+        }],
+    };
+
+    Some(Request::new(
+        RequestId::from(WATCH_REQUEST_ID.to_string()),
+        RegisterCapability::METHOD.to_string(),
+        RegistrationParams {
+            registrations: vec![Registration {
+                id: WATCH_REQUEST_ID.to_string(),
+                method: DidChangeWatchedFiles::METHOD.to_string(),
+                register_options: serde_json::to_value(options).ok(),
+            }],
+        },
+    ))
+}
+
+/// Whether `response` answers the request made by [`watch_request`]
+pub fn is_watch_response(response: &Response) -> bool {
+    response.id == RequestId::from(WATCH_REQUEST_ID.to_string())
+}
+
+pub fn is_custom_data_file(uri: &Url) -> bool {
+    uri.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|name| name == CUSTOM_DATA_FILE)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Url;
+
+    use super::{is_custom_data_file, CustomData};
+
+    #[test]
+    fn test_it_parses_attributes_and_extensions() {
+        let data = CustomData::parse(
+            r#"{
+                "attributes": [
+                    {
+                        "name": "hx-analytics-event",
+                        "description": "Sends an analytics event",
+                        "values": [{ "name": "signup" }]
+                    },
+                    { "name": "analytics-id" }
+                ],
+                "extensions": [{ "name": "analytics" }]
+            }"#,
+        )
+        .expect("valid custom data");
+
+        assert_eq!(data.attributes.len(), 1);
+        assert_eq!(
+            data.attribute("hx-analytics-event")
+                .map(|attribute| attribute.values[0].name.as_str()),
+            Some("signup")
+        );
+        assert_eq!(data.extensions[0].name, "analytics");
+        assert!(CustomData::parse(r#"{ "attributes": {} }"#).is_err());
+    }
+
+    #[test]
+    fn test_it_recognizes_the_data_file() {
+        let uri = |uri| Url::parse(uri).expect("valid uri");
+
+        assert!(is_custom_data_file(&uri("file:///project/htmx-lsp.json")));
+        assert!(!is_custom_data_file(&uri("file:///project/package.json")));
+    }
+}
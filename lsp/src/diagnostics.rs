@@ -0,0 +1,1044 @@
+use lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString};
+use tree_sitter::Node;
+
+use crate::{
+    config::{config, DiagnosticsConfig, HtmxVersion},
+    custom_data::custom_data,
+    htmx::{
+        hx_on::parse_legacy_hx_on,
+        hx_on_event_name,
+        json::{check_json_value, JSON_ATTRIBUTES},
+        prefix::with_prefix,
+        selector::{
+            id_matches, parse_selector_attribute, ExtendedSelector, ID_REFERENCE_ATTRIBUTES,
+        },
+        swap::{extension_swap_styles, parse_swap},
+        syntax::{split_list, tokenize, SyntaxError, Token},
+        trigger::parse_trigger,
+        version::{availability, Availability},
+        HX_ATTRIBUTE_VALUES, HX_EVENTS, HX_TAGS,
+    },
+    text_store::Document,
+    tree_sitter::range_to_lsp_range,
+    tree_sitter_querier::{
+        query_element_ids, query_enabled_extensions, query_htmx_attributes, AttributeValue,
+        HtmxAttribute,
+    },
+    workspace::{id_exists_in_workspace, is_indexing},
+};
+
+pub const DIAGNOSTIC_SOURCE: &str = "htmx-lsp";
+
+pub const UNKNOWN_ATTRIBUTE: &str = "unknown-attribute";
+pub const INVALID_SWAP: &str = "invalid-swap";
+pub const INVALID_TRIGGER: &str = "invalid-trigger";
+pub const UNKNOWN_ID: &str = "unknown-id";
+pub const HTMX_VERSION: &str = "htmx-version";
+pub const LEGACY_HX_ON: &str = "legacy-hx-on";
+pub const INVALID_JSON: &str = "invalid-json";
+pub const INVALID_SELECTOR: &str = "invalid-selector";
+pub const INVALID_BOOST: &str = "invalid-boost";
+pub const DUPLICATE_ATTRIBUTE: &str = "duplicate-attribute";
+pub const UNKNOWN_EVENT: &str = "unknown-event";
+
+/// `hx-on:click`, `hx-on::before-request` and their dashed variants carry the
+/// event in the attribute name, so they never match an entry in HX_TAGS
+const HX_ON_PREFIXES: &[&str] = &["hx-on:", "hx-on-"];
+
+/// What the rules know about the document besides the attribute being checked
+pub struct DocumentContext<'a> {
+    pub text: &'a str,
+    pub root: Node<'a>,
+    pub ids: Vec<AttributeValue>,
+    /// Every htmx attribute of the document, in document order
+    pub attributes: &'a [HtmxAttribute],
+    pub version: Option<HtmxVersion>,
+    /// Attributes the project defines in its custom data file
+    pub custom_attributes: Vec<String>,
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Returns the candidate closest to `word`, as long as it is close enough to
+/// be a plausible typo rather than a different word altogether
+pub fn closest_match<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (word.chars().count() / 3).max(2);
+
+    candidates
+        .map(|candidate| (edit_distance(word, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+pub fn create_diagnostic(
+    context: &DocumentContext,
+    range: tree_sitter::Range,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        range: range_to_lsp_range(context.text, range),
+        severity: Some(severity),
+        code: Some(NumberOrString::String(code.to_string())),
+        source: Some(DIAGNOSTIC_SOURCE.to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+fn syntax_errors_to_diagnostics(
+    context: &DocumentContext,
+    value: &AttributeValue,
+    errors: Vec<SyntaxError>,
+    severity: DiagnosticSeverity,
+    code: &str,
+) -> Vec<Diagnostic> {
+    errors
+        .into_iter()
+        .map(|err| {
+            create_diagnostic(
+                context,
+                value.sub_range(err.range),
+                severity,
+                code,
+                err.message,
+            )
+        })
+        .collect()
+}
+
+fn known_attributes(custom_attributes: &[String]) -> impl Iterator<Item = &str> + Clone {
+    HX_TAGS
+        .iter()
+        .map(|tag| tag.name.as_ref())
+        .chain(custom_attributes.iter().map(String::as_str))
+}
+
+/// Whether the canonical `name` is an attribute htmx or the project defines
+pub fn is_known_attribute(name: &str, custom_attributes: &[String]) -> bool {
+    known_attributes(custom_attributes).any(|known| known == name)
+        || HX_ON_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// The canonical attribute an unknown `name` is most likely a typo of
+pub fn suggest_attribute(name: &str, custom_attributes: &[String]) -> Option<String> {
+    closest_match(name, known_attributes(custom_attributes)).map(String::from)
+}
+
+fn unknown_attribute(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    let name = attribute.name.as_str();
+    if is_known_attribute(name, &context.custom_attributes) {
+        return vec![];
+    }
+
+    let written = attribute.written_name();
+    let message = match suggest_attribute(name, &context.custom_attributes) {
+        Some(suggestion) => format!(
+            "Unknown htmx attribute `{written}`, did you mean `{}`?",
+            with_prefix(&attribute.prefix, &suggestion)
+        ),
+        None => format!("Unknown htmx attribute `{written}`"),
+    };
+
+    vec![create_diagnostic(
+        context,
+        attribute.name_range,
+        DiagnosticSeverity::WARNING,
+        UNKNOWN_ATTRIBUTE,
+        message,
+    )]
+}
+
+/// The swap styles available to an element: those of the extensions enabled
+/// on it or its ancestors, and the hx-swap values of the custom data file
+pub fn extra_swap_styles(root: Node<'_>, text: &str, attribute: &HtmxAttribute) -> Vec<String> {
+    let enabled = query_enabled_extensions(root, text, attribute.name_range.start_point);
+    let custom = custom_data();
+    let config = config();
+    let defined = config
+        .extensions
+        .iter()
+        .chain(&custom.extensions)
+        .filter(|extension| enabled.contains(&extension.name))
+        .flat_map(|extension| extension.swap_styles.iter().cloned());
+    let values = custom
+        .attribute("hx-swap")
+        .map(|attribute| attribute.values.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .map(|value| value.name.clone());
+
+    extension_swap_styles(&enabled)
+        .into_iter()
+        .chain(defined)
+        .chain(values)
+        .collect()
+}
+
+fn invalid_swap(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    let value = match (attribute.name.as_str(), &attribute.value) {
+        ("hx-swap", Some(value)) => value,
+        _ => return vec![],
+    };
+
+    let spec = parse_swap(
+        &value.value,
+        &extra_swap_styles(context.root, context.text, attribute),
+    );
+    let mut diagnostics = syntax_errors_to_diagnostics(
+        context,
+        value,
+        spec.errors,
+        DiagnosticSeverity::ERROR,
+        INVALID_SWAP,
+    );
+    diagnostics.extend(syntax_errors_to_diagnostics(
+        context,
+        value,
+        spec.warnings,
+        DiagnosticSeverity::WARNING,
+        INVALID_SWAP,
+    ));
+
+    diagnostics
+}
+
+fn invalid_boost(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    let value = match (attribute.name.as_str(), &attribute.value) {
+        ("hx-boost", Some(value)) => value,
+        _ => return vec![],
+    };
+
+    let expected = HX_ATTRIBUTE_VALUES
+        .get("hx-boost")
+        .copied()
+        .unwrap_or_default();
+    if expected.iter().any(|item| item.name == value.value) {
+        return vec![];
+    }
+
+    // htmx only boosts on the exact `true`, anything else turns boosting off
+    let expected = expected
+        .iter()
+        .map(|item| format!("`{}`", item.name))
+        .collect::<Vec<_>>()
+        .join(" or ");
+    vec![create_diagnostic(
+        context,
+        value.range,
+        DiagnosticSeverity::ERROR,
+        INVALID_BOOST,
+        format!(
+            "Invalid hx-boost value `{}`, expected {expected}",
+            value.value
+        ),
+    )]
+}
+
+/// Whether the attribute repeats one written earlier on the same tag, with
+/// any prefix. Only the first one is read, by browsers and htmx alike.
+pub fn is_duplicate_attribute(
+    root: Node<'_>,
+    attributes: &[HtmxAttribute],
+    attribute: &HtmxAttribute,
+) -> bool {
+    let tag = match attribute.node(root).and_then(|node| node.parent()) {
+        Some(tag) => tag,
+        None => return false,
+    };
+
+    attributes
+        .iter()
+        .take_while(|other| other.name_range != attribute.name_range)
+        .filter(|other| other.name == attribute.name)
+        .filter_map(|other| other.node(root)?.parent())
+        .any(|other_tag| other_tag.id() == tag.id())
+}
+
+fn duplicate_attribute(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    if !is_duplicate_attribute(context.root, context.attributes, attribute) {
+        return vec![];
+    }
+
+    vec![create_diagnostic(
+        context,
+        attribute.name_range,
+        DiagnosticSeverity::WARNING,
+        DUPLICATE_ATTRIBUTE,
+        format!(
+            "`{}` is already set on this element, this one is ignored",
+            attribute.written_name()
+        ),
+    )]
+}
+
+fn invalid_trigger(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    match (attribute.name.as_str(), &attribute.value) {
+        ("hx-trigger", Some(value)) => syntax_errors_to_diagnostics(
+            context,
+            value,
+            parse_trigger(&value.value).errors,
+            DiagnosticSeverity::ERROR,
+            INVALID_TRIGGER,
+        ),
+        _ => vec![],
+    }
+}
+
+fn invalid_selector(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    let value = match &attribute.value {
+        Some(value) => value,
+        None => return vec![],
+    };
+
+    match parse_selector_attribute(&attribute.name, &value.value) {
+        Some(selectors) => syntax_errors_to_diagnostics(
+            context,
+            value,
+            selectors.errors,
+            DiagnosticSeverity::ERROR,
+            INVALID_SELECTOR,
+        ),
+        None => vec![],
+    }
+}
+
+/// Flags hx-vals and hx-headers values that are not JSON objects, which htmx
+/// drops without a word
+fn invalid_json(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    let value = match &attribute.value {
+        Some(value) if JSON_ATTRIBUTES.contains(&attribute.name.as_str()) => value,
+        _ => return vec![],
+    };
+
+    let check = check_json_value(&value.value);
+    let mut diagnostics = syntax_errors_to_diagnostics(
+        context,
+        value,
+        check.errors,
+        DiagnosticSeverity::ERROR,
+        INVALID_JSON,
+    );
+    diagnostics.extend(syntax_errors_to_diagnostics(
+        context,
+        value,
+        check.warnings,
+        DiagnosticSeverity::WARNING,
+        INVALID_JSON,
+    ));
+
+    diagnostics
+}
+
+fn unknown_id(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    let value = match &attribute.value {
+        Some(value) if ID_REFERENCE_ATTRIBUTES.contains(&attribute.name.as_str()) => value,
+        _ => return vec![],
+    };
+
+    // Ids declared in templates that are not scanned yet would be reported
+    if is_indexing() {
+        return vec![];
+    }
+
+    let selectors = match parse_selector_attribute(&attribute.name, &value.value) {
+        Some(selectors) => selectors.selectors,
+        None => return vec![],
+    };
+
+    selectors
+        .iter()
+        // Keywords like `closest #row` look the id up relative to the element
+        .filter(|selector| selector.prefix.is_none())
+        .flat_map(ExtendedSelector::ids)
+        .filter(|id| {
+            !context
+                .ids
+                .iter()
+                .any(|declared| id_matches(&declared.value, id.text))
+                && !id_exists_in_workspace(id.text)
+        })
+        .map(|id| {
+            create_diagnostic(
+                context,
+                value.sub_range(id.start - 1..id.end()),
+                DiagnosticSeverity::WARNING,
+                UNKNOWN_ID,
+                format!(
+                    "No element with id `{}` found in this document or the workspace",
+                    id.text
+                ),
+            )
+        })
+        .collect()
+}
+
+fn version_diagnostic(
+    context: &DocumentContext,
+    range: tree_sitter::Range,
+    availability: Availability,
+) -> Option<Diagnostic> {
+    let (severity, message, tags) = match availability {
+        Availability::Available => return None,
+        Availability::Deprecated(note) => (
+            DiagnosticSeverity::HINT,
+            note,
+            Some(vec![DiagnosticTag::DEPRECATED]),
+        ),
+        Availability::Removed(note) | Availability::NotYetAdded(note) => {
+            (DiagnosticSeverity::WARNING, note, None)
+        }
+    };
+
+    Some(Diagnostic {
+        tags,
+        ..create_diagnostic(context, range, severity, HTMX_VERSION, message)
+    })
+}
+
+/// Flags attributes and values the project's htmx version does not have, or
+/// that it deprecates
+fn htmx_version(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    let name = attribute.name.as_str();
+
+    // The legacy hx-on rule reports that one along with its fix
+    let mut diagnostics: Vec<_> = version_diagnostic(
+        context,
+        attribute.name_range,
+        availability(name, None, context.version),
+    )
+    .filter(|_| name != "hx-on")
+    .into_iter()
+    .collect();
+
+    let value = match &attribute.value {
+        Some(value) => value,
+        None => return diagnostics,
+    };
+
+    // The values that changed between versions are swap styles and extensions
+    let tokens: Vec<Token> = match name {
+        "hx-swap" => tokenize(&value.value).into_iter().take(1).collect(),
+        "hx-ext" => split_list(&value.value),
+        _ => vec![],
+    };
+
+    diagnostics.extend(tokens.into_iter().filter_map(|token| {
+        version_diagnostic(
+            context,
+            value.sub_range(token.range()),
+            availability(name, Some(token.text), context.version),
+        )
+    }));
+
+    diagnostics
+}
+
+/// Flags the `hx-on="event: code"` form, which htmx 2 replaced with one
+/// `hx-on:<event>` attribute per event
+fn legacy_hx_on(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    if attribute.name != "hx-on" {
+        return vec![];
+    }
+    match &attribute.value {
+        Some(value) if !parse_legacy_hx_on(&value.value).is_empty() => {}
+        _ => return vec![],
+    }
+
+    let (severity, tags) = match availability("hx-on", None, context.version) {
+        Availability::Removed(_) => (DiagnosticSeverity::WARNING, None),
+        _ => (
+            DiagnosticSeverity::HINT,
+            Some(vec![DiagnosticTag::DEPRECATED]),
+        ),
+    };
+    let message = format!(
+        "`{}` is the legacy syntax, use one `{}on:<event>` attribute per event",
+        attribute.written_name(),
+        attribute.prefix
+    );
+
+    vec![Diagnostic {
+        tags,
+        ..create_diagnostic(
+            context,
+            attribute.name_range,
+            severity,
+            LEGACY_HX_ON,
+            message,
+        )
+    }]
+}
+
+/// Flags `hx-on:*` attributes handling an event that is neither an htmx event
+/// nor a common DOM event. Only a hint, since extensions and the page itself
+/// can trigger any event.
+fn unknown_event(context: &DocumentContext, attribute: &HtmxAttribute) -> Vec<Diagnostic> {
+    let (event, events) = match hx_on_event_name(&attribute.name) {
+        Some((event, events)) if !event.is_empty() => (event, events),
+        _ => return vec![],
+    };
+    if events.iter().any(|item| item.name == event) {
+        return vec![];
+    }
+
+    let kind = match std::ptr::eq(events, HX_EVENTS) {
+        true => "htmx event",
+        false => "event",
+    };
+    let message = match closest_match(event, events.iter().map(|item| item.name.as_ref())) {
+        Some(suggestion) => format!("Unknown {kind} `{event}`, did you mean `{suggestion}`?"),
+        None => format!("Unknown {kind} `{event}`, make sure something triggers it"),
+    };
+
+    // The event ends the name, which is on a single line
+    let name_range = attribute.name_range;
+    let range = tree_sitter::Range {
+        start_byte: name_range.end_byte - event.len(),
+        start_point: tree_sitter::Point::new(
+            name_range.end_point.row,
+            name_range.end_point.column - event.len(),
+        ),
+        ..name_range
+    };
+
+    vec![create_diagnostic(
+        context,
+        range,
+        DiagnosticSeverity::HINT,
+        UNKNOWN_EVENT,
+        message,
+    )]
+}
+
+type Rule = fn(&DocumentContext, &HtmxAttribute) -> Vec<Diagnostic>;
+
+/// Every rule along with the diagnostic code it reports, which is also the
+/// name it is enabled or disabled by in the configuration
+const RULES: &[(&str, Rule)] = &[
+    (UNKNOWN_ATTRIBUTE, unknown_attribute),
+    (INVALID_SWAP, invalid_swap),
+    (INVALID_BOOST, invalid_boost),
+    (DUPLICATE_ATTRIBUTE, duplicate_attribute),
+    (INVALID_TRIGGER, invalid_trigger),
+    (INVALID_JSON, invalid_json),
+    (INVALID_SELECTOR, invalid_selector),
+    (UNKNOWN_ID, unknown_id),
+    (HTMX_VERSION, htmx_version),
+    (LEGACY_HX_ON, legacy_hx_on),
+    (UNKNOWN_EVENT, unknown_event),
+];
+
+pub fn document_diagnostics(
+    document: &Document,
+    config: &DiagnosticsConfig,
+    version: Option<HtmxVersion>,
+) -> Vec<Diagnostic> {
+    let rules: Vec<Rule> = RULES
+        .iter()
+        .filter(|(code, _)| config.is_rule_enabled(code))
+        .map(|(_, rule)| *rule)
+        .collect();
+    if rules.is_empty() {
+        return vec![];
+    }
+
+    let root = document.tree.root_node();
+    let attributes = query_htmx_attributes(root, &document.text);
+    let context = DocumentContext {
+        text: &document.text,
+        root,
+        ids: query_element_ids(root, &document.text),
+        attributes: &attributes,
+        version,
+        custom_attributes: custom_data()
+            .attributes
+            .iter()
+            .map(|attribute| attribute.name.clone())
+            .collect(),
+    };
+
+    attributes
+        .iter()
+        .flat_map(|attribute| rules.iter().flat_map(|rule| rule(&context, attribute)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest_match, edit_distance, unknown_attribute, DocumentContext};
+    use crate::{
+        config::{DiagnosticsConfig, HtmxVersion},
+        text_store::Document,
+        tree_sitter_querier::query_htmx_attributes,
+    };
+    use lsp_types::{Diagnostic, DiagnosticSeverity};
+    use lsp_types::{NumberOrString, Position, Range};
+
+    fn document_diagnostics(text: &str) -> Vec<Diagnostic> {
+        super::document_diagnostics(
+            &Document::new(text.to_string()),
+            &DiagnosticsConfig::default(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("hx-target", "hx-target"), 0);
+        assert_eq!(edit_distance("hx-tagret", "hx-target"), 2);
+        assert_eq!(edit_distance("hx-swp", "hx-swap"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_closest_match_ignores_unrelated_words() {
+        let candidates = ["hx-get", "hx-post"];
+
+        assert_eq!(
+            closest_match("hx-gte", candidates.iter().copied()),
+            Some("hx-get")
+        );
+        assert_eq!(
+            closest_match("hx-analytics", candidates.iter().copied()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_it_reports_misspelled_attributes_with_suggestion() {
+        let text = r##"<div hx-get="/foo" hx-tagret="#results"></div>"##;
+
+        let diagnostics = document_diagnostics(text);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(0, 19), Position::new(0, 28))
+        );
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("unknown-attribute".to_string()))
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "Unknown htmx attribute `hx-tagret`, did you mean `hx-target`?"
+        );
+    }
+
+    #[test]
+    fn test_it_treats_data_prefixed_attributes_like_hx_attributes() {
+        let text = r##"<div data-hx-get="/foo" data-hx-swap="outerHtml" data-hx-tagret="#results"></div>"##;
+
+        let diagnostics = document_diagnostics(text);
+
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Unknown hx-swap style `outerHtml`",
+                "Unknown htmx attribute `data-hx-tagret`, did you mean `data-hx-target`?",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_it_accepts_custom_attributes() {
+        let text = r#"<div hx-analytics-event="signup" hx-analytics-evnt="signup"></div>"#;
+        let document = Document::new(text.to_string());
+        let context = DocumentContext {
+            text,
+            root: document.tree.root_node(),
+            ids: vec![],
+            attributes: &[],
+            version: None,
+            custom_attributes: vec!["hx-analytics-event".to_string()],
+        };
+
+        let messages: Vec<_> = query_htmx_attributes(document.tree.root_node(), text)
+            .iter()
+            .flat_map(|attribute| unknown_attribute(&context, attribute))
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec!["Unknown htmx attribute `hx-analytics-evnt`, did you mean `hx-analytics-event`?"]
+        );
+    }
+
+    #[test]
+    fn test_it_accepts_known_and_hx_on_attributes() {
+        let text = r#"<button hx-post="/clicked" hx-swap="outerHTML" hx-on:click="go()" hx-on::after-request="done()">
+        </button>"#;
+
+        assert!(document_diagnostics(text).is_empty());
+    }
+
+    #[test]
+    fn test_it_reports_invalid_swap_values_inside_the_attribute() {
+        let text = r#"<div hx-get="/foo"
+     hx-swap="outerHTML swap:1sec"></div>"#;
+
+        let diagnostics = document_diagnostics(text);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 30), Position::new(1, 33))
+        );
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("invalid-swap".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_it_accepts_swap_styles_of_enabled_extensions() {
+        let text = r##"<div hx-ext="morph, multi-swap">
+  <button hx-swap="morph:outerHTML"></button>
+  <a hx-swap="multi:#a,#b" hx-ext="ignore:multi-swap"></a>
+</div>
+<p hx-swap="morph"></p>"##;
+
+        let diagnostics = document_diagnostics(text);
+
+        let reported: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.range.start.line, d.severity, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                (
+                    2,
+                    Some(DiagnosticSeverity::WARNING),
+                    "Swap style `multi:#a,#b` needs the `multi-swap` extension, enable it with hx-ext"
+                ),
+                (
+                    4,
+                    Some(DiagnosticSeverity::WARNING),
+                    "Swap style `morph` needs the `morph` extension, enable it with hx-ext"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_it_reports_malformed_triggers() {
+        let text = r#"<input hx-get="/search" hx-trigger="keyup changed delay:500 sec">"#;
+
+        let diagnostics = document_diagnostics(text);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(0, 60), Position::new(0, 63))
+        );
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("invalid-trigger".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_it_reports_invalid_json_inside_the_attribute() {
+        let text = r#"<div hx-vals='{"page": 1,}' hx-headers='{"a": 1, "a": 2}'></div><p data-hx-vals="js:{page: next()}"></p>"#;
+
+        let diagnostics = document_diagnostics(text);
+
+        let reported: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.range, d.severity, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                (
+                    Range::new(Position::new(0, 25), Position::new(0, 26)),
+                    Some(DiagnosticSeverity::ERROR),
+                    "Invalid JSON: trailing comma"
+                ),
+                (
+                    Range::new(Position::new(0, 49), Position::new(0, 52)),
+                    Some(DiagnosticSeverity::WARNING),
+                    "Duplicate key `a`, only its last value is used"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_it_reports_invalid_boost_values_and_duplicated_attributes() {
+        let text = r#"<a hx-boost="ture" hx-get="/a" data-hx-get="/b"></a><a hx-get="/c" hx-boost="false"></a>"#;
+
+        let reported: Vec<_> = document_diagnostics(text)
+            .into_iter()
+            .map(|d| {
+                (
+                    d.range.start.character,
+                    d.range.end.character,
+                    d.code,
+                    d.message,
+                )
+            })
+            .collect();
+        let code = |code: &str| Some(NumberOrString::String(code.to_string()));
+
+        assert_eq!(
+            reported,
+            vec![
+                (
+                    13,
+                    17,
+                    code("invalid-boost"),
+                    "Invalid hx-boost value `ture`, expected `true` or `false`".to_string()
+                ),
+                (
+                    31,
+                    42,
+                    code("duplicate-attribute"),
+                    "`data-hx-get` is already set on this element, this one is ignored".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_it_reports_malformed_selectors() {
+        let text = r#"<div hx-target="div >" hx-trigger="click target:.a:"></div>"#;
+
+        let diagnostics = document_diagnostics(text);
+
+        let reported: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.range, d.code.clone(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                (
+                    Range::new(Position::new(0, 20), Position::new(0, 21)),
+                    Some(NumberOrString::String("invalid-selector".to_string())),
+                    "Expected a selector after `>`"
+                ),
+                (
+                    Range::new(Position::new(0, 50), Position::new(0, 51)),
+                    Some(NumberOrString::String("invalid-trigger".to_string())),
+                    "Expected a name after `:`"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_it_reports_ids_missing_from_the_document() {
+        let text = r##"<button hx-get="/rows" hx-target="#rows" hx-include="#filters, closest form, #missing"></button>
+<table id="rows"></table>
+<form id="filters"></form>"##;
+
+        let diagnostics = document_diagnostics(text);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(0, 77), Position::new(0, 85))
+        );
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("unknown-id".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_it_skips_extended_selectors_for_missing_ids() {
+        let text = r##"<tr hx-get="/edit" hx-target="this" hx-indicator="closest #spinner" hx-disabled-elt="find #save, next .b"></tr>"##;
+
+        assert!(document_diagnostics(text).is_empty());
+    }
+
+    #[test]
+    fn test_it_reads_missing_ids_from_parsed_selectors() {
+        let text = r##"<tr hx-target="#row-{{ id }}" hx-swap-oob="outerHTML:#gone" hx-include="prev #x"></tr>"##;
+
+        let ranges: Vec<_> = document_diagnostics(text)
+            .into_iter()
+            .map(|diagnostic| diagnostic.range.start.character..diagnostic.range.end.character)
+            .collect();
+
+        // `prev` is not an htmx keyword but an element name
+        assert_eq!(ranges, vec![53..58, 77..79]);
+    }
+
+    #[test]
+    fn test_it_reports_ranges_in_utf16_after_non_ascii_text() {
+        let text = r#"<p title="日本語 👋" hx-swp="innerHTML">Grüße</p>"#;
+
+        let diagnostics = document_diagnostics(text);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(0, 18), Position::new(0, 24))
+        );
+    }
+
+    #[test]
+    fn test_it_skips_rules_disabled_in_the_configuration() {
+        let text = r##"<div hx-tagret="#missing" hx-swap="outerHtml"></div>"##;
+        let mut config = DiagnosticsConfig::default();
+        config
+            .rules
+            .insert(super::UNKNOWN_ATTRIBUTE.to_string(), false);
+
+        let diagnostics =
+            super::document_diagnostics(&Document::new(text.to_string()), &config, None);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String(super::INVALID_SWAP.to_string()))
+        );
+
+        config.enable = false;
+        assert!(
+            super::document_diagnostics(&Document::new(text.to_string()), &config, None).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_it_follows_the_htmx_version() {
+        let text = r#"<div hx-sse="connect:/events" hx-inherit="*" hx-swap="textContent" hx-ext="json-enc, disable-element"></div>"#;
+        let diagnose = |version| {
+            super::document_diagnostics(
+                &Document::new(text.to_string()),
+                &DiagnosticsConfig::default(),
+                Some(version),
+            )
+        };
+
+        let v1: Vec<_> = diagnose(HtmxVersion::V1)
+            .into_iter()
+            .map(|d| (d.severity.unwrap(), d.message))
+            .collect();
+        assert_eq!(
+            v1,
+            vec![
+                (
+                    DiagnosticSeverity::HINT,
+                    "`hx-sse` is deprecated, use the `sse` extension with `sse-connect` and `sse-swap` instead".to_string()
+                ),
+                (
+                    DiagnosticSeverity::WARNING,
+                    "`hx-inherit` requires htmx 2, but the project uses htmx 1".to_string()
+                ),
+                (
+                    DiagnosticSeverity::WARNING,
+                    "`textContent` requires htmx 2, but the project uses htmx 1".to_string()
+                ),
+                (
+                    DiagnosticSeverity::HINT,
+                    "`disable-element` is deprecated, use `hx-disabled-elt` instead".to_string()
+                ),
+            ]
+        );
+
+        let v2 = diagnose(HtmxVersion::V2);
+        assert_eq!(v2.len(), 2);
+        assert_eq!(
+            v2[0].range,
+            Range::new(Position::new(0, 5), Position::new(0, 11))
+        );
+        assert_eq!(
+            v2[1].range,
+            Range::new(Position::new(0, 85), Position::new(0, 100))
+        );
+    }
+
+    #[test]
+    fn test_it_reports_the_legacy_hx_on_form() {
+        let text = r#"<button hx-on="htmx:beforeRequest: start()" hx-on:click="go()"></button>"#;
+        let diagnose = |version| {
+            super::document_diagnostics(
+                &Document::new(text.to_string()),
+                &DiagnosticsConfig::default(),
+                version,
+            )
+        };
+
+        let v1 = diagnose(None);
+        assert_eq!(v1.len(), 1);
+        assert_eq!(
+            v1[0].code,
+            Some(NumberOrString::String("legacy-hx-on".to_string()))
+        );
+        assert_eq!(v1[0].severity, Some(DiagnosticSeverity::HINT));
+        assert_eq!(
+            v1[0].range,
+            Range::new(Position::new(0, 8), Position::new(0, 13))
+        );
+
+        let v2 = diagnose(Some(HtmxVersion::V2));
+        assert_eq!(v2.len(), 1);
+        assert_eq!(v2[0].severity, Some(DiagnosticSeverity::WARNING));
+    }
+
+    #[test]
+    fn test_it_hints_at_unknown_hx_on_events() {
+        let text = r#"<button hx-on::befor-request="a()" data-hx-on:clik="b()" hx-on-my-event="c()"
+        hx-on:htmx:after-swap="d()" hx-on--xhr:loadend="e()" hx-on:click="f()"></button>"#;
+
+        let hints: Vec<_> = document_diagnostics(text)
+            .into_iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::HINT));
+                assert_eq!(
+                    diagnostic.code,
+                    Some(NumberOrString::String("unknown-event".to_string()))
+                );
+                (diagnostic.range, diagnostic.message)
+            })
+            .collect();
+
+        assert_eq!(
+            hints,
+            vec![
+                (
+                    Range::new(Position::new(0, 15), Position::new(0, 28)),
+                    "Unknown htmx event `befor-request`, did you mean `before-request`?"
+                        .to_string()
+                ),
+                (
+                    Range::new(Position::new(0, 46), Position::new(0, 50)),
+                    "Unknown event `clik`, did you mean `click`?".to_string()
+                ),
+                (
+                    Range::new(Position::new(0, 63), Position::new(0, 71)),
+                    "Unknown event `my-event`, make sure something triggers it".to_string()
+                ),
+            ]
+        );
+    }
+}
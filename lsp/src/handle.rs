@@ -1,15 +1,29 @@
 use crate::{
-    htmx::{hx_completion, hx_hover, HxDocItem},
-    text_store::TEXT_STORE,
+    code_actions::code_actions,
+    config::{
+        config, configuration_request, is_configuration_response, set_config, Config,
+        DiagnosticsConfig,
+    },
+    custom_data::{is_custom_data_file, is_watch_response, load_custom_data},
+    diagnostics::document_diagnostics,
+    htmx::version::htmx_version,
+    htmx::{
+        hx_completion, hx_definition, hx_hover, hx_resolve, patterns::PatternCompletion, HxDocItem,
+    },
+    text_store::{
+        get_document, open_documents, remove_text_document, update_text_document, Document,
+        TEXT_STORE,
+    },
+    workspace::{index_document, reindex_from_disk, rescan_workspace, workspace_root_paths},
 };
 use log::{debug, error, warn};
 use lsp_server::{Message, Notification, Request, RequestId};
-use lsp_types::{CompletionContext, CompletionParams, CompletionTriggerKind, HoverParams};
-
-#[derive(serde::Deserialize, Debug)]
-struct Text {
-    text: String,
-}
+use lsp_types::{
+    CodeActionOrCommand, CodeActionParams, CompletionContext, CompletionItem, CompletionParams,
+    CompletionTriggerKind, Diagnostic, DidChangeConfigurationParams, DidChangeWatchedFilesParams,
+    DidCloseTextDocumentParams, DidSaveTextDocumentParams, Documentation, GotoDefinitionParams,
+    HoverParams, Location, MarkupContent, MarkupKind, Range, TextDocumentContentChangeEvent, Url,
+};
 
 #[derive(serde::Deserialize, Debug)]
 struct TextDocumentLocation {
@@ -22,7 +36,7 @@ struct TextDocumentChanges {
     text_document: TextDocumentLocation,
 
     #[serde(rename = "contentChanges")]
-    content_changes: Vec<Text>,
+    content_changes: Vec<TextDocumentContentChangeEvent>,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -41,7 +55,23 @@ struct TextDocumentOpen {
 #[derive(Debug)]
 pub struct HtmxAttributeCompletion {
     pub items: Vec<HxDocItem>,
+    /// The typed text that accepting an item replaces
+    pub range: Range,
+    /// The attribute whose value is completed, kept in the items so that
+    /// resolving them can find their documentation
+    pub attribute: Option<String>,
+    /// Whether attribute names are inserted as snippets with their value
+    pub snippets: bool,
+    pub patterns: Vec<PatternCompletion>,
+    /// The document completed in, whose htmx version decides what is offered
+    pub uri: Url,
+    pub id: RequestId,
+}
+
+#[derive(Debug)]
+pub struct HtmxCompletionResolve {
     pub id: RequestId,
+    pub item: Box<CompletionItem>,
 }
 
 #[derive(Debug)]
@@ -50,12 +80,140 @@ pub struct HtmxAttributeHoverResult {
     pub value: String,
 }
 
+#[derive(Debug)]
+pub struct HtmxDefinitionResult {
+    pub id: RequestId,
+    pub locations: Vec<Location>,
+}
+
+#[derive(Debug)]
+pub struct HtmxCodeActionResult {
+    pub id: RequestId,
+    pub actions: Vec<CodeActionOrCommand>,
+}
+
+#[derive(Debug)]
+pub struct HtmxDiagnostics {
+    pub uri: Url,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
 #[derive(Debug)]
 pub enum HtmxResult {
-    // Diagnostic,
+    Diagnostics(HtmxDiagnostics),
+
     AttributeCompletion(HtmxAttributeCompletion),
 
+    CompletionResolve(HtmxCompletionResolve),
+
     AttributeHover(HtmxAttributeHoverResult),
+
+    Definition(HtmxDefinitionResult),
+
+    CodeActions(HtmxCodeActionResult),
+
+    WorkspaceDiagnostics(Vec<HtmxDiagnostics>),
+
+    ConfigurationRequest(Request),
+}
+
+fn diagnose(uri: &str, document: &Document, config: &DiagnosticsConfig) -> Option<HtmxDiagnostics> {
+    let version = htmx_version(Some(uri));
+    let uri = match Url::parse(uri) {
+        Ok(uri) => uri,
+        Err(err) => {
+            error!("publish_diagnostics invalid uri {uri}: {:?}", err);
+            return None;
+        }
+    };
+
+    Some(HtmxDiagnostics {
+        uri,
+        diagnostics: document_diagnostics(document, config, version),
+    })
+}
+
+fn publish_diagnostics(uri: &str, document: &Document) -> Option<HtmxResult> {
+    diagnose(uri, document, &config().diagnostics).map(HtmxResult::Diagnostics)
+}
+
+/// Switches to new settings and refreshes everything that depends on them
+fn apply_config(new: Config) -> Option<HtmxResult> {
+    let rescan = config().template_globs != new.template_globs;
+    set_config(new);
+
+    if rescan {
+        rescan_workspace();
+    }
+
+    // Rules may have been turned on or off
+    republish_diagnostics(&config().diagnostics)
+}
+
+/// Diagnoses every open document again, e.g. after what counts as valid
+/// has changed
+fn republish_diagnostics(config: &DiagnosticsConfig) -> Option<HtmxResult> {
+    Some(HtmxResult::WorkspaceDiagnostics(open_document_diagnostics(
+        config,
+    )))
+}
+
+fn open_document_diagnostics(config: &DiagnosticsConfig) -> Vec<HtmxDiagnostics> {
+    open_documents()
+        .iter()
+        .filter_map(|(uri, document)| diagnose(uri, document, config))
+        .collect()
+}
+
+/// Diagnoses every open document again once the workspace scan is over, ids
+/// it found may be the ones missing before
+pub fn handle_scans_finished() -> Option<HtmxResult> {
+    republish_diagnostics(&config().diagnostics)
+}
+
+/// Diagnoses the changed document, or every open document when its ids
+/// changed since those may be referred to elsewhere
+fn publish_changed_document(
+    uri: &str,
+    document: &Document,
+    ids_changed: bool,
+) -> Option<HtmxResult> {
+    match ids_changed {
+        true => republish_diagnostics(&config().diagnostics),
+        false => publish_diagnostics(uri, document),
+    }
+}
+
+/// Reloads the custom data files, whose attributes every open document may use
+fn reload_custom_data() -> Option<HtmxResult> {
+    load_custom_data(&workspace_root_paths());
+    republish_diagnostics(&config().diagnostics)
+}
+
+#[allow(non_snake_case)]
+fn handle_didChangeWatchedFiles(noti: Notification) -> Option<HtmxResult> {
+    let params: DidChangeWatchedFilesParams = serde_json::from_value(noti.params).ok()?;
+
+    if params
+        .changes
+        .iter()
+        .any(|change| is_custom_data_file(&change.uri))
+    {
+        return reload_custom_data();
+    }
+
+    None
+}
+
+#[allow(non_snake_case)]
+fn handle_didChangeConfiguration(noti: Notification) -> Option<HtmxResult> {
+    let params: DidChangeConfigurationParams = serde_json::from_value(noti.params).ok()?;
+
+    // Clients that support pulling settings usually send no settings here
+    match Config::from_value(&params.settings) {
+        Some(config) => apply_config(config),
+        None => configuration_request().map(HtmxResult::ConfigurationRequest),
+    }
 }
 
 // ignore snakeCase
@@ -63,20 +221,10 @@ pub enum HtmxResult {
 fn handle_didChange(noti: Notification) -> Option<HtmxResult> {
     let text_document_changes: TextDocumentChanges = serde_json::from_value(noti.params).ok()?;
     let uri = text_document_changes.text_document.uri;
-    let text = text_document_changes.content_changes[0].text.to_string();
-
-    if text_document_changes.content_changes.len() > 1 {
-        error!("more than one content change, please be wary");
-    }
-
-    TEXT_STORE
-        .get()
-        .expect("text store not initialized")
-        .lock()
-        .expect("text store mutex poisoned")
-        .insert(uri, text);
+    let document = update_text_document(&uri, text_document_changes.content_changes)?;
 
-    None
+    let ids_changed = index_document(&uri, &document);
+    publish_changed_document(&uri, &document, ids_changed)
 }
 
 #[allow(non_snake_case)]
@@ -90,14 +238,54 @@ fn handle_didOpen(noti: Notification) -> Option<HtmxResult> {
         }
     };
 
+    let uri = text_document_changes.uri;
+    let document = Document::new(text_document_changes.text);
+    let ids_changed = index_document(&uri, &document);
+
     TEXT_STORE
         .get()
         .expect("text store not initialized")
         .lock()
         .expect("text store mutex poisoned")
-        .insert(text_document_changes.uri, text_document_changes.text);
+        .insert(uri.clone(), document.clone());
 
-    None
+    publish_changed_document(&uri, &document, ids_changed)
+}
+
+#[allow(non_snake_case)]
+fn handle_didSave(noti: Notification) -> Option<HtmxResult> {
+    let params: DidSaveTextDocumentParams = serde_json::from_value(noti.params).ok()?;
+    let uri = params.text_document.uri;
+
+    if is_custom_data_file(&uri) {
+        return reload_custom_data();
+    }
+
+    let document = get_document(&uri)?;
+    publish_diagnostics(uri.as_str(), &document)
+}
+
+#[allow(non_snake_case)]
+fn handle_didClose(noti: Notification) -> Option<HtmxResult> {
+    let params: DidCloseTextDocumentParams = serde_json::from_value(noti.params).ok()?;
+    let uri = params.text_document.uri;
+
+    remove_text_document(uri.as_str());
+    let ids_changed = reindex_from_disk(uri.as_str());
+
+    // Diagnostics of closed documents are no longer kept up to date
+    let cleared = HtmxDiagnostics {
+        uri,
+        diagnostics: vec![],
+    };
+
+    if !ids_changed {
+        return Some(HtmxResult::Diagnostics(cleared));
+    }
+
+    let mut diagnostics = open_document_diagnostics(&config().diagnostics);
+    diagnostics.push(cleared);
+    Some(HtmxResult::WorkspaceDiagnostics(diagnostics))
 }
 
 #[allow(non_snake_case)]
@@ -115,8 +303,9 @@ fn handle_completion(req: Request) -> Option<HtmxResult> {
             trigger_kind: CompletionTriggerKind::INVOKED,
             ..
         }) => {
-            let items = match hx_completion(completion.text_document_position) {
-                Some(items) => items,
+            let uri = completion.text_document_position.text_document.uri.clone();
+            let completion_result = match hx_completion(completion.text_document_position) {
+                Some(completion_result) => completion_result,
                 None => {
                     error!("EMPTY RESULTS OF COMPLETION");
                     return None;
@@ -125,11 +314,16 @@ fn handle_completion(req: Request) -> Option<HtmxResult> {
 
             error!(
                 "handled result: {:?}: completion result: {:?}",
-                completion.context, items
+                completion.context, completion_result
             );
 
             Some(HtmxResult::AttributeCompletion(HtmxAttributeCompletion {
-                items: items.to_vec(),
+                items: completion_result.items,
+                range: completion_result.range,
+                attribute: completion_result.attribute,
+                snippets: completion_result.snippets,
+                patterns: completion_result.patterns,
+                uri,
                 id: req.id,
             }))
         }
@@ -140,6 +334,26 @@ fn handle_completion(req: Request) -> Option<HtmxResult> {
     }
 }
 
+/// Fills in the documentation left out of the completion list
+fn handle_completion_resolve(req: Request) -> Option<HtmxResult> {
+    let mut item: CompletionItem = serde_json::from_value(req.params).ok()?;
+    let data = |key: &str| item.data.as_ref()?.get(key)?.as_str();
+    let attribute = data("attribute");
+    let version = htmx_version(data("uri"));
+
+    if let Some(doc) = hx_resolve(attribute, &item.label, version) {
+        item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: doc.desc.to_string(),
+        }));
+    }
+
+    Some(HtmxResult::CompletionResolve(HtmxCompletionResolve {
+        id: req.id,
+        item: Box::new(item),
+    }))
+}
+
 fn handle_hover(req: Request) -> Option<HtmxResult> {
     let hover: HoverParams = serde_json::from_value(req.params).ok()?;
     debug!("handle_hover: {:?}", hover);
@@ -158,11 +372,40 @@ fn handle_hover(req: Request) -> Option<HtmxResult> {
     }))
 }
 
+fn handle_definition(req: Request) -> Option<HtmxResult> {
+    let definition: GotoDefinitionParams = serde_json::from_value(req.params).ok()?;
+    debug!("handle_definition: {:?}", definition);
+
+    let locations = hx_definition(definition.text_document_position_params)?;
+
+    Some(HtmxResult::Definition(HtmxDefinitionResult {
+        id: req.id,
+        locations,
+    }))
+}
+
+fn handle_code_action(req: Request) -> Option<HtmxResult> {
+    let params: CodeActionParams = serde_json::from_value(req.params).ok()?;
+    debug!("handle_code_action: {:?}", params);
+
+    let uri = params.text_document.uri;
+    let document = get_document(&uri)?;
+    let actions = code_actions(&uri, &document, params.range, &params.context.diagnostics);
+
+    Some(HtmxResult::CodeActions(HtmxCodeActionResult {
+        id: req.id,
+        actions,
+    }))
+}
+
 pub fn handle_request(req: Request) -> Option<HtmxResult> {
     error!("handle_request");
     match req.method.as_str() {
         "textDocument/completion" => handle_completion(req),
+        "completionItem/resolve" => handle_completion_resolve(req),
         "textDocument/hover" => handle_hover(req),
+        "textDocument/definition" => handle_definition(req),
+        "textDocument/codeAction" => handle_code_action(req),
         _ => {
             warn!("unhandled request: {:?}", req);
             None
@@ -174,6 +417,10 @@ pub fn handle_notification(noti: Notification) -> Option<HtmxResult> {
     match noti.method.as_str() {
         "textDocument/didChange" => handle_didChange(noti),
         "textDocument/didOpen" => handle_didOpen(noti),
+        "textDocument/didSave" => handle_didSave(noti),
+        "textDocument/didClose" => handle_didClose(noti),
+        "workspace/didChangeConfiguration" => handle_didChangeConfiguration(noti),
+        "workspace/didChangeWatchedFiles" => handle_didChangeWatchedFiles(noti),
         s => {
             debug!("unhandled notification: {:?}", s);
             None
@@ -182,20 +429,36 @@ pub fn handle_notification(noti: Notification) -> Option<HtmxResult> {
 }
 
 pub fn handle_other(msg: Message) -> Option<HtmxResult> {
-    warn!("unhandled message {:?}", msg);
-    None
+    match msg {
+        Message::Response(response) if is_configuration_response(&response) => {
+            apply_config(Config::from_response(&response)?)
+        }
+        Message::Response(response) if is_watch_response(&response) => {
+            if let Some(err) = response.error {
+                warn!("failed to watch the custom data files: {:?}", err);
+            }
+            None
+        }
+        _ => {
+            warn!("unhandled message {:?}", msg);
+            None
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{handle_request, HtmxResult, Request};
-    use crate::text_store::{init_text_store, TEXT_STORE};
+    use super::{handle_notification, handle_request, HtmxResult, Notification, Request};
+    use crate::text_store::{init_text_store, Document, TEXT_STORE};
+    use crate::workspace::{index_document, init_workspace_index};
+    use lsp_types::{Documentation, Position, Range};
     use std::sync::Once;
 
     static SETUP: Once = Once::new();
     fn prepare_store(file: &str, content: &str) {
         SETUP.call_once(|| {
             init_text_store();
+            init_workspace_index();
         });
 
         TEXT_STORE
@@ -203,7 +466,7 @@ mod tests {
             .expect("text store not initialized")
             .lock()
             .expect("text store mutex poisoned")
-            .insert(file.to_string(), content.to_string());
+            .insert(file.to_string(), Document::new(content.to_string()));
     }
 
     #[test]
@@ -241,6 +504,299 @@ mod tests {
         }
     }
 
+    fn hover(file: &str, line: u32, character: u32) -> Option<String> {
+        let req = Request {
+            id: 1.into(),
+            method: "textDocument/hover".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+                "position": {
+                    "line": line,
+                    "character": character
+                }
+            }),
+        };
+
+        match handle_request(req) {
+            Some(HtmxResult::AttributeHover(h)) => Some(h.value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn handle_hover_it_presents_details_of_the_value_under_cursor() {
+        let file = "file:///valuehover.html";
+        let content = r#"<div hx-swap="outerHTML swap:1s" hx-ext="json-enc, debug"
+     hx-trigger="keyup changed delay:500ms"></div>"#;
+
+        prepare_store(file, content);
+
+        let value = hover(file, 0, 17).expect("swap style docs");
+        assert!(value.starts_with("hx-swap outerHTML"), "{value}");
+        assert!(value.contains("replaces the entire target element"));
+
+        // Swap modifiers have no documentation of their own
+        assert!(hover(file, 0, 26).is_none());
+
+        let value = hover(file, 0, 53).expect("extension docs");
+        assert!(value.starts_with("hx-ext debug"), "{value}");
+
+        let value = hover(file, 1, 28).expect("trigger modifier docs");
+        assert!(value.starts_with("hx-trigger changed"), "{value}");
+
+        let value = hover(file, 1, 36).expect("trigger modifier docs");
+        assert!(value.starts_with("hx-trigger delay:"), "{value}");
+    }
+
+    #[test]
+    fn handle_completion_it_suggests_time_intervals_after_delay() {
+        let file = "file:///triggercompletion.html";
+        let content = r#"<input hx-trigger="keyup delay:">"#;
+
+        prepare_store(file, content);
+
+        let req = Request {
+            id: 1.into(),
+            method: "textDocument/completion".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+                "position": {
+                    "line": 0,
+                    "character": 31
+                },
+                "context": {
+                    "triggerKind": 1
+                }
+            }),
+        };
+
+        match handle_request(req) {
+            Some(HtmxResult::AttributeCompletion(c)) => {
+                let names: Vec<_> = c.items.iter().map(|item| item.name.as_ref()).collect();
+                assert!(names.contains(&"500ms"), "{:?}", names);
+                assert!(!names.contains(&"click"), "{:?}", names);
+                // Only the text after `delay:` is replaced
+                assert_eq!(
+                    c.range,
+                    Range::new(Position::new(0, 31), Position::new(0, 31))
+                );
+                assert_eq!(c.attribute.as_deref(), Some("hx-trigger"));
+            }
+            result => {
+                panic!("unexpected result: {:?}", result);
+            }
+        }
+    }
+
+    #[test]
+    fn handle_completion_it_suggests_previous_for_hx_target() {
+        let file = "file:///targetcompletion.html";
+        let content = r#"<div hx-target=""></div>"#;
+
+        prepare_store(file, content);
+
+        let req = Request {
+            id: 1.into(),
+            method: "textDocument/completion".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+                "position": {
+                    "line": 0,
+                    "character": 16
+                },
+                "context": {
+                    "triggerKind": 1
+                }
+            }),
+        };
+
+        match handle_request(req) {
+            Some(HtmxResult::AttributeCompletion(c)) => {
+                let names: Vec<_> = c.items.iter().map(|item| item.name.as_ref()).collect();
+                assert!(names.contains(&"previous"), "{:?}", names);
+                assert!(!names.contains(&"prev"), "{:?}", names);
+            }
+            result => {
+                panic!("unexpected result: {:?}", result);
+            }
+        }
+    }
+
+    #[test]
+    fn handle_completion_it_keeps_the_data_prefix() {
+        let file = "file:///dataprefixcompletion.html";
+        let content = r#"<div data-hx- ></div>"#;
+
+        prepare_store(file, content);
+
+        let req = Request {
+            id: 1.into(),
+            method: "textDocument/completion".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+                "position": {
+                    "line": 0,
+                    "character": 13
+                },
+                "context": {
+                    "triggerKind": 1
+                }
+            }),
+        };
+
+        match handle_request(req) {
+            Some(HtmxResult::AttributeCompletion(c)) => {
+                let names: Vec<_> = c.items.iter().map(|item| item.name.as_ref()).collect();
+                assert!(names.contains(&"data-hx-get"), "{:?}", names);
+                assert!(names.iter().all(|name| name.starts_with("data-hx-")));
+                assert_eq!(
+                    c.range,
+                    Range::new(Position::new(0, 5), Position::new(0, 13))
+                );
+                assert_eq!(c.attribute, None);
+            }
+            result => {
+                panic!("unexpected result: {:?}", result);
+            }
+        }
+    }
+
+    #[test]
+    fn handle_completion_it_ranks_companions_and_hides_present_attributes() {
+        let file = "file:///companioncompletion.html";
+        let content = r#"<div hx-get="/foo" hx- ></div>"#;
+
+        prepare_store(file, content);
+
+        let req = Request {
+            id: 1.into(),
+            method: "textDocument/completion".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+                "position": {
+                    "line": 0,
+                    "character": 22
+                },
+                "context": {
+                    "triggerKind": 1
+                }
+            }),
+        };
+
+        match handle_request(req) {
+            Some(HtmxResult::AttributeCompletion(c)) => {
+                let names: Vec<_> = c.items.iter().map(|item| item.name.as_ref()).collect();
+                assert_eq!(names[..3], ["hx-target", "hx-swap", "hx-trigger"]);
+                assert!(!names.contains(&"hx-get"), "{:?}", names);
+                assert!(names.contains(&"hx-post"), "{:?}", names);
+            }
+            result => {
+                panic!("unexpected result: {:?}", result);
+            }
+        }
+    }
+
+    #[test]
+    fn handle_completion_it_suggests_events_for_hx_on() {
+        let file = "file:///hxoncompletion.html";
+        let content = r#"<button hx-on:click="go()" hx-on:: ></button>"#;
+
+        prepare_store(file, content);
+
+        let req = Request {
+            id: 1.into(),
+            method: "textDocument/completion".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+                "position": {
+                    "line": 0,
+                    "character": 34
+                },
+                "context": {
+                    "triggerKind": 2,
+                    "triggerCharacter": ":"
+                }
+            }),
+        };
+
+        match handle_request(req) {
+            Some(HtmxResult::AttributeCompletion(c)) => {
+                let names: Vec<_> = c.items.iter().map(|item| item.name.as_ref()).collect();
+                assert!(names.contains(&"hx-on::after-swap"), "{:?}", names);
+                assert!(names.contains(&"hx-on:submit"), "{:?}", names);
+                assert!(!names.contains(&"hx-on:click"), "{:?}", names);
+                assert!(!names.contains(&"hx-get"), "{:?}", names);
+                assert_eq!(
+                    c.range,
+                    Range::new(Position::new(0, 27), Position::new(0, 34))
+                );
+            }
+            result => {
+                panic!("unexpected result: {:?}", result);
+            }
+        }
+    }
+
+    #[test]
+    fn handle_hover_it_presents_event_details_for_hx_on() {
+        let file = "file:///hxonhover.html";
+        let content = r#"<form hx-on::config-request="go(event)" data-hx-on:submit="x()"></form>"#;
+
+        prepare_store(file, content);
+
+        let value = hover(file, 0, 20).expect("htmx event docs");
+        assert!(value.starts_with("htmx:configRequest"), "{value}");
+        assert!(value.contains("parameters - "), "{value}");
+
+        let value = hover(file, 0, 55).expect("DOM event docs");
+        assert!(value.starts_with("submit"), "{value}");
+    }
+
+    #[test]
+    fn handle_completion_resolve_it_adds_the_documentation() {
+        let resolve = |label: &str, attribute: Option<&str>| {
+            let req = Request {
+                id: 1.into(),
+                method: "completionItem/resolve".to_string(),
+                params: serde_json::json!({
+                    "label": label,
+                    "data": { "attribute": attribute }
+                }),
+            };
+
+            match handle_request(req) {
+                Some(HtmxResult::CompletionResolve(resolve)) => resolve.item.documentation,
+                result => panic!("unexpected result: {:?}", result),
+            }
+        };
+
+        match resolve("data-hx-target", None) {
+            Some(Documentation::MarkupContent(doc)) => {
+                assert!(doc.value.starts_with("hx-target"), "{}", doc.value)
+            }
+            doc => panic!("unexpected documentation: {:?}", doc),
+        }
+        match resolve("outerHTML", Some("hx-swap")) {
+            Some(Documentation::MarkupContent(doc)) => {
+                assert!(doc.value.contains("replaces the entire target element"))
+            }
+            doc => panic!("unexpected documentation: {:?}", doc),
+        }
+        assert_eq!(resolve("hx-nothing", None), None);
+    }
+
     #[test]
     fn handle_hover_it_presents_details_of_the_tag_name_when_is_under_cursor() {
         let file = "file:///detailstag.html";
@@ -275,4 +831,191 @@ mod tests {
             }
         }
     }
+
+    fn definition_request(file: &str, character: u32) -> Request {
+        Request {
+            id: 1.into(),
+            method: "textDocument/definition".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+                "position": {
+                    "line": 0,
+                    "character": character
+                }
+            }),
+        }
+    }
+
+    #[test]
+    fn handle_definition_it_jumps_to_the_element_in_the_same_document() {
+        let file = "file:///definition.html";
+        let content =
+            r##"<button hx-get="/search" hx-target="#results">Go</button><ul id="results"></ul>"##;
+
+        prepare_store(file, content);
+
+        match handle_request(definition_request(file, 40)) {
+            Some(HtmxResult::Definition(d)) => {
+                assert_eq!(d.locations.len(), 1);
+                assert_eq!(d.locations[0].uri.as_str(), file);
+                assert_eq!(d.locations[0].range.start.character, 65);
+                assert_eq!(d.locations[0].range.end.character, 72);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn handle_definition_it_falls_back_to_the_workspace() {
+        let file = "file:///definition_source.html";
+        let other = "file:///partials/definition_target.html";
+        let content = r##"<form hx-post="/save" hx-indicator="closest #spinner-x"></form>"##;
+
+        prepare_store(file, content);
+        index_document(
+            other,
+            &Document::new(
+                r#"<div>
+  <img id="spinner-x" src="/spinner.gif">
+</div>"#
+                    .to_string(),
+            ),
+        );
+
+        match handle_request(definition_request(file, 47)) {
+            Some(HtmxResult::Definition(d)) => {
+                assert_eq!(d.locations.len(), 1);
+                assert_eq!(d.locations[0].uri.as_str(), other);
+                assert_eq!(d.locations[0].range.start.line, 1);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // Not on an id reference
+        assert!(handle_request(definition_request(file, 39)).is_none());
+    }
+
+    #[test]
+    fn handle_definition_it_follows_selectors_of_trigger_modifiers() {
+        let file = "file:///definition_trigger.html";
+        let content = r##"<input hx-trigger="keyup from:#search-box"><input id="search-box">"##;
+
+        prepare_store(file, content);
+
+        match handle_request(definition_request(file, 33)) {
+            Some(HtmxResult::Definition(d)) => {
+                assert_eq!(d.locations.len(), 1);
+                assert_eq!(d.locations[0].range.start.character, 54);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn handle_completion_it_suggests_document_ids_in_selectors() {
+        let file = "file:///selectorcompletion.html";
+        let content =
+            r#"<div hx-target="closest #"></div><ul id="results"></ul><p id="row-{{ id }}"></p>"#;
+
+        prepare_store(file, content);
+
+        let req = Request {
+            id: 1.into(),
+            method: "textDocument/completion".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+                "position": {
+                    "line": 0,
+                    "character": 25
+                },
+                "context": {
+                    "triggerKind": 1
+                }
+            }),
+        };
+
+        match handle_request(req) {
+            Some(HtmxResult::AttributeCompletion(c)) => {
+                let names: Vec<_> = c.items.iter().map(|item| item.name.as_ref()).collect();
+                assert_eq!(names, vec!["results"]);
+                assert_eq!(
+                    c.range,
+                    Range::new(Position::new(0, 25), Position::new(0, 25))
+                );
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn handle_notification_it_rediagnoses_open_documents_when_ids_change() {
+        let referring = "file:///refers-to-late.html";
+        let declaring = "file:///declares-late.html";
+
+        prepare_store(referring, r##"<div hx-target="#late-id"></div>"##);
+        prepare_store(declaring, "<div></div>");
+
+        let result = handle_notification(Notification {
+            method: "textDocument/didChange".to_string(),
+            params: serde_json::json!({
+                "textDocument": { "uri": declaring, "version": 2 },
+                "contentChanges": [{ "text": r#"<div id="late-id"></div>"# }],
+            }),
+        });
+
+        match result {
+            Some(HtmxResult::WorkspaceDiagnostics(all)) => {
+                let referring = all
+                    .iter()
+                    .find(|d| d.uri.as_str() == referring)
+                    .expect("referring document diagnosed again");
+                assert!(referring.diagnostics.is_empty());
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // Edits that keep the same ids only concern the edited document
+        let result = handle_notification(Notification {
+            method: "textDocument/didChange".to_string(),
+            params: serde_json::json!({
+                "textDocument": { "uri": declaring, "version": 3 },
+                "contentChanges": [{ "text": r#"<p id="late-id"></p>"# }],
+            }),
+        });
+        assert!(matches!(result, Some(HtmxResult::Diagnostics(_))));
+    }
+
+    #[test]
+    fn handle_notification_it_evicts_closed_documents() {
+        let file = "file:///closed.html";
+
+        prepare_store(file, r#"<div hx-get="/foo"></div>"#);
+
+        let result = handle_notification(Notification {
+            method: "textDocument/didClose".to_string(),
+            params: serde_json::json!({
+                "textDocument": {
+                    "uri": file,
+                },
+            }),
+        });
+
+        match result {
+            Some(HtmxResult::Diagnostics(d)) => {
+                assert_eq!(d.uri.as_str(), file);
+                assert!(d.diagnostics.is_empty());
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(!TEXT_STORE
+            .get()
+            .expect("text store not initialized")
+            .lock()
+            .expect("text store mutex poisoned")
+            .contains_key(file));
+    }
 }
@@ -0,0 +1,187 @@
+// See: https://v1.htmx.org/attributes/hx-on/
+
+use super::syntax::Token;
+
+/// One `event: code` handler of a legacy `hx-on` value
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegacyHandler<'a> {
+    pub event: Token<'a>,
+    pub code: Token<'a>,
+}
+
+/// Finds the `event:` a line starts with, mirroring the
+/// `/^\s*([a-zA-Z:\-\.]+:)(.*)/` htmx 1 matches lines against. Returns the
+/// byte range of the event, without its colon.
+fn event_prefix(line: &str) -> Option<(usize, usize)> {
+    let start = line.len() - line.trim_start().len();
+    let run = line[start..]
+        .find(|c: char| !(c.is_ascii_alphabetic() || matches!(c, ':' | '-' | '.')))
+        .unwrap_or(line.len() - start);
+
+    // The regex backtracks to the last colon of the run
+    let colon = line[start..start + run].rfind(':')?;
+    (colon > 0).then_some((start, start + colon))
+}
+
+fn trimmed(value: &str, start: usize, end: usize) -> Token<'_> {
+    let text = &value[start..end];
+    let trimmed = text.trim_start();
+
+    Token {
+        text: trimmed.trim_end(),
+        start: start + text.len() - trimmed.len(),
+    }
+}
+
+/// Splits a legacy `hx-on` value into its handlers the way htmx 1 does: a
+/// line starting with `event:` starts a handler, unless it is inside the
+/// braces of the previous one, and other lines continue the previous handler
+pub fn parse_legacy_hx_on(value: &str) -> Vec<LegacyHandler<'_>> {
+    let mut handlers: Vec<(Token, usize, usize)> = vec![];
+    let mut curly_count = 0i32;
+    let mut line_start = 0;
+
+    for line in value.split('\n') {
+        let line_end = line_start + line.len();
+
+        let code_start = match event_prefix(line).filter(|_| curly_count == 0) {
+            Some((start, end)) => {
+                let event = Token {
+                    text: &line[start..end],
+                    start: line_start + start,
+                };
+                handlers.push((event, line_start + end + 1, line_end));
+                end + 1
+            }
+            None => {
+                if let Some((_, _, code_end)) = handlers.last_mut() {
+                    *code_end = line_end;
+                }
+                0
+            }
+        };
+
+        curly_count += line[code_start..]
+            .chars()
+            .map(|c| match c {
+                '{' => 1,
+                '}' => -1,
+                _ => 0,
+            })
+            .sum::<i32>();
+        line_start = line_end + 1;
+    }
+
+    handlers
+        .into_iter()
+        .map(|(event, code_start, code_end)| LegacyHandler {
+            event,
+            code: trimmed(value, code_start, code_end),
+        })
+        .collect()
+}
+
+fn kebab_case(name: &str) -> String {
+    name.chars().fold(String::new(), |mut kebab, c| {
+        if c.is_ascii_uppercase() {
+            kebab.push('-');
+        }
+        kebab.push(c.to_ascii_lowercase());
+        kebab
+    })
+}
+
+/// The `hx-on:*` attribute handling `event`, e.g. `hx-on::before-request` for
+/// `htmx:beforeRequest`. Attribute names are not case sensitive, which is why
+/// htmx also fires its events in kebab-case. Other events are only fired the
+/// way they are written, so there is no attribute for a camelCase one.
+pub fn hx_on_attribute_name(prefix: &str, event: &str) -> Option<String> {
+    match event.strip_prefix("htmx:") {
+        Some(htmx_event) => Some(format!("{prefix}on::{}", kebab_case(htmx_event))),
+        None if event.chars().any(|c| c.is_ascii_uppercase()) => None,
+        None => Some(format!("{prefix}on:{event}")),
+    }
+}
+
+/// Rewrites a legacy `hx-on` value as one `hx-on:*` attribute per handler,
+/// quoted with `quote` and joined by `separator`. Values handling a camelCase
+/// event of their own are left alone, no attribute would catch it.
+pub fn migrate_legacy_hx_on(
+    prefix: &str,
+    value: &str,
+    quote: char,
+    separator: &str,
+) -> Option<String> {
+    let handlers = parse_legacy_hx_on(value);
+    if handlers.is_empty() {
+        return None;
+    }
+
+    let attributes = handlers
+        .iter()
+        .map(|handler| {
+            let name = hx_on_attribute_name(prefix, handler.event.text)?;
+            Some(format!("{name}={quote}{}{quote}", handler.code.text))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(attributes.join(separator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hx_on_attribute_name, migrate_legacy_hx_on, parse_legacy_hx_on};
+
+    #[test]
+    fn test_it_splits_handlers_by_line_and_braces() {
+        let value = "htmx:beforeRequest: if (busy) {\n  event.preventDefault()\n}\n  click:go()";
+        let handlers = parse_legacy_hx_on(value);
+
+        let parsed: Vec<_> = handlers
+            .iter()
+            .map(|h| (h.event.text, h.event.start, h.code.text))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (
+                    "htmx:beforeRequest",
+                    0,
+                    "if (busy) {\n  event.preventDefault()\n}"
+                ),
+                ("click", 61, "go()"),
+            ]
+        );
+        assert!(parse_legacy_hx_on("alert('no event')").is_empty());
+    }
+
+    #[test]
+    fn test_it_rewrites_handlers_as_attributes() {
+        assert_eq!(
+            hx_on_attribute_name("hx-", "htmx:afterRequest").as_deref(),
+            Some("hx-on::after-request")
+        );
+        assert_eq!(
+            hx_on_attribute_name("data-hx-", "click").as_deref(),
+            Some("data-hx-on:click")
+        );
+
+        let value = "htmx:beforeRequest: start()\nhtmx:afterRequest: stop()";
+        assert_eq!(
+            migrate_legacy_hx_on("hx-", value, '\'', "\n     ").as_deref(),
+            Some("hx-on::before-request='start()'\n     hx-on::after-request='stop()'")
+        );
+    }
+
+    #[test]
+    fn test_it_leaves_camel_case_custom_events_alone() {
+        assert_eq!(hx_on_attribute_name("hx-", "myEvent"), None);
+        assert_eq!(
+            hx_on_attribute_name("hx-", "my-event").as_deref(),
+            Some("hx-on:my-event")
+        );
+
+        let value = "click: go()\nmyEvent: refresh()";
+        assert_eq!(migrate_legacy_hx_on("hx-", value, '"', " "), None);
+    }
+}
@@ -0,0 +1,342 @@
+// See: https://htmx.org/attributes/hx-vals/ and https://htmx.org/attributes/hx-headers/
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use super::{selector::is_templated, syntax::SyntaxError};
+
+/// Attributes whose value is a JSON object
+pub const JSON_ATTRIBUTES: &[&str] = &["hx-vals", "hx-headers"];
+
+/// Values starting with these are evaluated as javascript instead of parsed
+const DYNAMIC_PREFIXES: &[&str] = &["javascript:", "js:"];
+
+/// Character references authors use to write quotes inside the attribute
+const NAMED_ENTITIES: &[(&str, char)] = &[
+    ("quot", '"'),
+    ("apos", '\''),
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("nbsp", '\u{a0}'),
+];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct JsonCheck {
+    /// Problems that make htmx ignore the value
+    pub errors: Vec<SyntaxError>,
+    /// Valid JSON that probably doesn't send what the author meant
+    pub warnings: Vec<SyntaxError>,
+}
+
+fn is_dynamic(value: &str) -> bool {
+    let value = value.trim_start();
+
+    DYNAMIC_PREFIXES
+        .iter()
+        .any(|prefix| value.starts_with(prefix))
+}
+
+/// The character a reference like `&quot;` or `&#34;` at the start of `text`
+/// stands for, along with the length of the reference
+fn entity(text: &str) -> Option<(char, usize)> {
+    let len = text.strip_prefix('&')?.find(';')? + 2;
+    let name = &text[1..len - 1];
+
+    let c = match name.strip_prefix('#') {
+        Some(code) => match code.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => code.parse().ok(),
+        }
+        .and_then(char::from_u32)?,
+        None => NAMED_ENTITIES.iter().find(|(entity, _)| *entity == name)?.1,
+    };
+
+    Some((c, len))
+}
+
+/// The value as the browser reads it, along with the offset in `value` of
+/// each byte of the decoded text and of its end
+fn decode_entities(value: &str) -> (String, Vec<usize>) {
+    let mut decoded = String::with_capacity(value.len());
+    let mut offsets = Vec::with_capacity(value.len() + 1);
+    let mut rest = value;
+
+    while let Some(c) = rest.chars().next() {
+        let (c, len) = entity(rest).unwrap_or((c, c.len_utf8()));
+
+        offsets.extend(std::iter::repeat_n(value.len() - rest.len(), c.len_utf8()));
+        decoded.push(c);
+        rest = &rest[len..];
+    }
+    offsets.push(value.len());
+
+    (decoded, offsets)
+}
+
+/// Byte offset of a 1-based line and column as serde_json reports them
+fn error_offset(value: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = value
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+
+    let mut offset = (line_start + column.saturating_sub(1)).min(value.len());
+    while !value.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    offset
+}
+
+fn syntax_error(value: &str, err: &serde_json::Error) -> SyntaxError {
+    let start = error_offset(value, err.line(), err.column());
+    let end = value[start..]
+        .chars()
+        .next()
+        .map_or(start, |c| start + c.len_utf8());
+
+    // The position is part of the range already
+    let message = err.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message);
+
+    SyntaxError::new(start..end, format!("Invalid JSON: {message}"))
+}
+
+/// Byte offset right after the closing quote of the string starting at `start`
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut idx = start + 1;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' => idx += 2,
+            b'"' => return idx + 1,
+            _ => idx += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// Keys repeated within the same object. Browsers keep the last value, so
+/// the earlier ones are silently dropped. Expects valid JSON.
+fn duplicate_keys(value: &str) -> Vec<SyntaxError> {
+    let bytes = value.as_bytes();
+    // Keys seen in each enclosing object, None for arrays
+    let mut scopes: Vec<Option<HashSet<String>>> = vec![];
+    let mut expects_key = false;
+    let mut errors = vec![];
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'{' => {
+                scopes.push(Some(HashSet::new()));
+                expects_key = true;
+            }
+            b'[' => {
+                scopes.push(None);
+                expects_key = false;
+            }
+            b'}' | b']' => {
+                scopes.pop();
+                expects_key = false;
+            }
+            b',' => expects_key = matches!(scopes.last(), Some(Some(_))),
+            b':' => expects_key = false,
+            b'"' => {
+                let end = string_end(bytes, idx);
+
+                if let (true, Some(Some(keys))) = (expects_key, scopes.last_mut()) {
+                    let key: String = serde_json::from_str(&value[idx..end]).unwrap_or_default();
+                    if !keys.insert(key.clone()) {
+                        errors.push(SyntaxError::new(
+                            idx..end,
+                            format!("Duplicate key `{key}`, only its last value is used"),
+                        ));
+                    }
+                }
+
+                idx = end;
+                continue;
+            }
+            _ => {}
+        }
+
+        idx += 1;
+    }
+
+    errors
+}
+
+/// Checks the value of an hx-vals or hx-headers attribute, skipping the
+/// `js:` form that is only known at runtime and values a template builds.
+/// Like htmx, a value that doesn't start with `{` is read as the content of
+/// an object, so `"page": 2` is fine.
+pub fn check_json_value(value: &str) -> JsonCheck {
+    if is_dynamic(value) || is_templated(value) || value.trim() == "unset" {
+        return JsonCheck::default();
+    }
+
+    let (decoded, offsets) = decode_entities(value);
+    let trimmed = decoded.trim();
+    let lead = decoded.len() - decoded.trim_start().len();
+    let (json, wrapped) = match trimmed.starts_with('{') {
+        true => (trimmed.to_string(), false),
+        false => (format!("{{{trimmed}}}"), true),
+    };
+
+    // Back from the checked JSON to the attribute value, the braces htmx adds
+    // map to the ends of the value
+    let original = |offset: usize| {
+        let offset = match wrapped {
+            true => offset.saturating_sub(1).min(trimmed.len()),
+            false => offset,
+        };
+        offsets[lead + offset]
+    };
+    let map = |errors: Vec<SyntaxError>| {
+        errors
+            .into_iter()
+            .map(|err| {
+                SyntaxError::new(
+                    original(err.range.start)..original(err.range.end),
+                    err.message,
+                )
+            })
+            .collect()
+    };
+
+    match serde_json::from_str::<Value>(&json) {
+        Err(err) => JsonCheck {
+            errors: map(vec![syntax_error(&json, &err)]),
+            warnings: vec![],
+        },
+        Ok(_) => JsonCheck {
+            errors: vec![],
+            warnings: map(duplicate_keys(&json)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_json_value;
+
+    type Reported = Vec<(usize, usize, String)>;
+
+    fn ranges(value: &str) -> (Reported, Reported) {
+        let check = check_json_value(value);
+        let list = |errors: Vec<super::SyntaxError>| {
+            errors
+                .into_iter()
+                .map(|err| (err.range.start, err.range.end, err.message))
+                .collect()
+        };
+
+        (list(check.errors), list(check.warnings))
+    }
+
+    #[test]
+    fn test_it_accepts_objects_and_dynamic_values() {
+        assert_eq!(
+            ranges(r#"{"id": 1, "nested": {"id": 2}}"#),
+            (vec![], vec![])
+        );
+        assert_eq!(ranges(" js:{id: getId()}"), (vec![], vec![]));
+        assert_eq!(ranges("javascript:{id: 1, id: 2}"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_it_reports_syntax_errors_where_they_are() {
+        let (errors, _) = ranges("{\"a\": 1,\n \"b\" 2}");
+        assert_eq!(
+            errors,
+            vec![(14, 15, "Invalid JSON: expected `:`".to_string())]
+        );
+
+        let (errors, _) = ranges("{'a': 1}");
+        assert_eq!(
+            errors,
+            vec![(1, 2, "Invalid JSON: key must be a string".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_it_warns_about_duplicate_keys() {
+        assert_eq!(
+            ranges(r#"{"a": 1, "b": {"a": "x,\"a\""}, "a": [{"b": 1}]}"#),
+            (
+                vec![],
+                vec![(
+                    32,
+                    35,
+                    "Duplicate key `a`, only its last value is used".to_string()
+                )]
+            )
+        );
+    }
+
+    #[test]
+    fn test_it_reads_values_without_braces_as_objects() {
+        assert_eq!(ranges(r#" "page": 2, "q": "htmx" "#), (vec![], vec![]));
+        assert_eq!(
+            ranges(r#""a": 1, "a": 2"#),
+            (
+                vec![],
+                vec![(
+                    8,
+                    11,
+                    "Duplicate key `a`, only its last value is used".to_string()
+                )]
+            )
+        );
+
+        let (errors, _) = ranges(r#" "a" 1"#);
+        assert_eq!(
+            errors,
+            vec![(5, 6, "Invalid JSON: expected `:`".to_string())]
+        );
+
+        let (errors, _) = ranges(" [1, 2] ");
+        assert_eq!(
+            errors,
+            vec![(1, 2, "Invalid JSON: key must be a string".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_it_skips_templated_values() {
+        assert_eq!(ranges(r#"{"id": {{ item.id }}}"#), (vec![], vec![]));
+        assert_eq!(ranges(r#""id": <%= @item.id %>"#), (vec![], vec![]));
+        assert_eq!(ranges("unset"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_it_decodes_character_references() {
+        assert_eq!(ranges("{&quot;a&quot;: &#34;b&#x22;}"), (vec![], vec![]));
+
+        let value = "{&quot;a&quot;: 1, &quot;a&quot;: 2}";
+        assert_eq!(
+            ranges(value),
+            (
+                vec![],
+                vec![(
+                    19,
+                    32,
+                    "Duplicate key `a`, only its last value is used".to_string()
+                )]
+            )
+        );
+
+        let (errors, _) = ranges("{&quot;a&quot; 1}");
+        assert_eq!(
+            errors,
+            vec![(15, 16, "Invalid JSON: expected `:`".to_string())]
+        );
+    }
+}
@@ -1,38 +1,505 @@
+pub mod hx_on;
+pub mod json;
+pub mod patterns;
+pub mod prefix;
+pub mod selector;
+pub mod swap;
+pub mod syntax;
+pub mod trigger;
+pub mod version;
+
+use std::{borrow::Cow, sync::OnceLock};
+
 use log::debug;
-use lsp_types::TextDocumentPositionParams;
+use lsp_types::{InitializeParams, Location, Range, TextDocumentPositionParams};
 use serde::{Deserialize, Serialize};
 
-use crate::{text_store::get_word_from_pos_params, tree_sitter::Position};
+use crate::{
+    config::{config, ExtensionDefinition, HtmxVersion},
+    custom_data::{custom_data, CustomData},
+    text_store::{get_document, get_text_after, get_typed_range, get_word_from_pos_params},
+    tree_sitter::{
+        get_attribute_name_at_position, get_attribute_value_at_position, get_sibling_attributes,
+        Position,
+    },
+    tree_sitter_querier::HtmxAttribute,
+    workspace::{document_ids, find_id_in_workspace},
+};
+
+use self::{
+    patterns::{pattern_completions, PatternCompletion},
+    prefix::{attribute_prefixes, canonical_name, split_prefix, with_prefix, CANONICAL_PREFIX},
+    selector::{
+        is_templated, parse_selector_attribute, selector_completion, selector_id_at,
+        ExtendedSelector, SelectorCompletion, SELECTOR_ATTRIBUTES,
+    },
+    syntax::{split_list, tokenize, Token},
+    trigger::{parse_trigger, trigger_completion, trigger_word_at, TriggerCompletion},
+    version::{availability, available_items, htmx_version, Availability},
+};
 
+/// Documentation for an attribute or value. The built in catalog borrows
+/// static markdown, while items from the user's configuration own theirs.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HxDocItem {
-    pub name: &'static str,
-    pub desc: &'static str,
+    pub name: Cow<'static, str>,
+    pub desc: Cow<'static, str>,
 }
 
 macro_rules! build_completion {
     ($(($name:expr, $desc:expr)),*) => {
         &[
             $(HxDocItem {
-            name: $name,
-            desc: include_str!($desc),
+            name: Cow::Borrowed($name),
+            desc: Cow::Borrowed(include_str!($desc)),
             }),*
         ]
     };
 }
 
-pub fn hx_completion(text_params: TextDocumentPositionParams) -> Option<&'static [HxDocItem]> {
+/// Completion items along with the typed text they replace
+#[derive(Debug)]
+pub struct HxCompletion {
+    pub items: Vec<HxDocItem>,
+    pub range: Range,
+    /// The canonical attribute whose value is completed, None when completing
+    /// attribute names
+    pub attribute: Option<String>,
+    /// Whether attribute names are inserted as snippets with their value
+    pub snippets: bool,
+    /// Multi attribute recipes, offered alongside attribute names
+    pub patterns: Vec<PatternCompletion>,
+}
+
+static SNIPPET_SUPPORT: OnceLock<bool> = OnceLock::new();
+
+/// Remembers whether the client expands snippets in completion items
+pub fn init_snippet_support(params: &InitializeParams) {
+    let supported = params
+        .capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.completion.as_ref())
+        .and_then(|completion| completion.completion_item.as_ref())
+        .and_then(|item| item.snippet_support)
+        .unwrap_or(false);
+
+    _ = SNIPPET_SUPPORT.set(supported);
+}
+
+/// Attributes that only take one of a few values, which snippets offer as
+/// choices. Other values, like selectors or extension lists, are left to
+/// completion so that typing them isn't in the way of a choice list.
+const ENUMERATED_VALUES: &[&str] = &["hx-swap", "hx-boost", "hx-history"];
+
+fn escape_snippet(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('$', "\\$")
+        .replace('}', "\\}")
+}
+
+fn escape_choice(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('|', "\\|")
+}
+
+/// Inserts the attribute with its quotes and the cursor between them, e.g.
+/// `hx-get="$1"`, offering the values of enumerated attributes as choices
+pub fn attribute_snippet(name: &str, version: Option<HtmxVersion>) -> String {
+    let choices = canonical_name(name)
+        .filter(|canonical| ENUMERATED_VALUES.contains(&canonical.as_str()))
+        .map(|canonical| {
+            let items = attribute_value_items(&custom_data(), &canonical);
+            available_items(Some(&canonical), &items, version)
+        })
+        .unwrap_or_default();
+
+    let name = escape_snippet(name);
+    if choices.is_empty() {
+        return format!("{name}=\"$1\"");
+    }
+
+    let choices = choices
+        .iter()
+        .map(|choice| escape_choice(&choice.name))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{name}=\"${{1|{choices}|}}\"")
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.' | '@')
+}
+
+/// Value words stop at `:` and `,` so that e.g. `delay:` and the items of
+/// an `hx-ext` list are kept when the word after them is replaced
+fn is_value_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | '.')
+}
+
+pub fn hx_completion(text_params: TextDocumentPositionParams) -> Option<HxCompletion> {
     let result = crate::tree_sitter::get_position_from_lsp_completion(text_params.clone())?;
 
     debug!("result: {:?} params: {:?}", result, text_params);
 
+    let version = htmx_version(Some(text_params.text_document.uri.as_str()));
+    let custom = custom_data();
+
     match result {
-        Position::AttributeName(name) => name.starts_with("hx-").then_some(HX_TAGS),
-        Position::AttributeValue { name, .. } => HX_ATTRIBUTE_VALUES.get(&name).copied(),
+        Position::AttributeName(name) => {
+            let (prefix, canonical) = split_prefix(&name, &attribute_prefixes())?;
+            let present = get_sibling_attributes(text_params.clone());
+            let items = match canonical.starts_with(HX_ON_PREFIX) {
+                true => hx_on_items(),
+                false => available_items(None, &attribute_items(&custom), version),
+            };
+            let items: Vec<_> = items
+                .into_iter()
+                .filter(|item| !present.iter().any(|name| *name == item.name))
+                .collect();
+            let items = rank_companions(items, &present);
+
+            // Names that already have a value are only renamed
+            let has_value = get_text_after(&text_params)
+                .map(|rest| rest.trim_start_matches(is_name_char).starts_with('='))
+                .unwrap_or(false);
+
+            let snippets = SNIPPET_SUPPORT.get().copied().unwrap_or(false) && !has_value;
+
+            Some(HxCompletion {
+                items: prefixed_items(&items, prefix),
+                range: get_typed_range(&text_params, is_name_char)?,
+                attribute: None,
+                snippets,
+                patterns: match snippets {
+                    true => pattern_completions(&config().patterns, prefix),
+                    false => vec![],
+                },
+            })
+        }
+        Position::AttributeValue { name, .. } => {
+            let name = canonical_name(&name)?;
+            let range = get_typed_range(&text_params, is_value_char)?;
+            let items = match name.as_str() {
+                "hx-trigger" => hx_trigger_completion(text_params)?.to_vec(),
+                "hx-ext" => available_items(Some("hx-ext"), &all_extension_items(&custom), version),
+                name if SELECTOR_ATTRIBUTES.contains(&name) => {
+                    let items = hx_selector_completion(&text_params, name, &custom)?;
+                    if items.is_empty() {
+                        return None;
+                    }
+                    available_items(Some(name), &items, version)
+                }
+                name => {
+                    let items = attribute_value_items(&custom, name);
+                    if items.is_empty() {
+                        return None;
+                    }
+                    available_items(Some(name), &items, version)
+                }
+            };
+
+            Some(HxCompletion {
+                items,
+                range,
+                attribute: Some(name),
+                snippets: false,
+                patterns: vec![],
+            })
+        }
+    }
+}
+
+/// The documentation of a completion item, which clients ask for separately
+/// once the item is selected
+pub fn hx_resolve(
+    attribute: Option<&str>,
+    label: &str,
+    version: Option<HtmxVersion>,
+) -> Option<HxDocItem> {
+    let custom = custom_data();
+    let find = |items: &[HxDocItem], name: &str| items.iter().find(|x| x.name == name).cloned();
+
+    let item = match attribute {
+        None => {
+            let name = canonical_name(label)?;
+            if let Some(item) = hx_on_event(&name) {
+                return Some(HxDocItem {
+                    name: Cow::Owned(label.to_string()),
+                    desc: item.desc,
+                });
+            }
+
+            let item = find(&attribute_items(&custom), &name)?;
+            with_availability_note(item, &name, None, version)
+        }
+        Some(attribute) => {
+            let item = match attribute {
+                "hx-trigger" => [
+                    HX_TRIGGER_EVENTS,
+                    HX_TRIGGER_MODIFIERS,
+                    HX_TRIGGER_INTERVALS,
+                    HX_TRIGGER_FROM,
+                    HX_TRIGGER_QUEUE,
+                ]
+                .into_iter()
+                .find_map(|items| find(items, label)),
+                "hx-ext" => find(&all_extension_items(&custom), label),
+                attribute => find(&attribute_value_items(&custom, attribute), label),
+            }?;
+            with_availability_note(item, attribute, Some(label), version)
+        }
+    };
+
+    Some(HxDocItem {
+        name: Cow::Owned(label.to_string()),
+        desc: item.desc,
+    })
+}
+
+/// `hx-on:` is followed by a DOM event, `hx-on::` by an htmx event
+const HX_ON_PREFIX: &str = "hx-on:";
+
+/// Every `hx-on:<event>` and `hx-on::<htmx-event>` attribute, documented by
+/// their event
+fn hx_on_items() -> Vec<HxDocItem> {
+    let named = |format: &str, item: &HxDocItem| HxDocItem {
+        name: Cow::Owned(format!("{format}{}", item.name)),
+        desc: item.desc.clone(),
+    };
+
+    DOM_EVENTS
+        .iter()
+        .map(|item| named(HX_ON_PREFIX, item))
+        .chain(HX_EVENTS.iter().map(|item| named("hx-on::", item)))
+        .collect()
+}
+
+/// The event an `hx-on` attribute handles, in any of the forms htmx accepts:
+/// `hx-on::after-swap`, `hx-on:htmx:after-swap`, `hx-on--after-swap`,
+/// `hx-on:click` and `hx-on-click`, along with the events it is one of
+pub fn hx_on_event_name(name: &str) -> Option<(&str, &'static [HxDocItem])> {
+    if let Some(event) = ["hx-on::", "hx-on:htmx:", "hx-on--", "hx-on-htmx-"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+    {
+        return Some((event, HX_EVENTS));
+    }
+
+    let event = name
+        .strip_prefix(HX_ON_PREFIX)
+        .or_else(|| name.strip_prefix("hx-on-"))?;
+    Some((event, DOM_EVENTS))
+}
+
+/// The documentation of the event an `hx-on` attribute handles
+fn hx_on_event(name: &str) -> Option<HxDocItem> {
+    let (event, events) = hx_on_event_name(name)?;
+
+    events.iter().find(|x| x.name == event).cloned()
+}
+
+/// Attributes that usually go along with another one on the same element
+const COMPANIONS: &[(&str, &[&str])] = &[
+    ("hx-get", &["hx-target", "hx-swap", "hx-trigger"]),
+    ("hx-post", &["hx-target", "hx-swap", "hx-trigger"]),
+    ("hx-put", &["hx-target", "hx-swap", "hx-trigger"]),
+    ("hx-patch", &["hx-target", "hx-swap", "hx-trigger"]),
+    ("hx-delete", &["hx-target", "hx-swap", "hx-confirm"]),
+    ("hx-target", &["hx-swap", "hx-select"]),
+    ("hx-trigger", &["hx-sync"]),
+    ("hx-select", &["hx-target"]),
+];
+
+/// Moves the companions of the attributes already on the element to the
+/// front, in the order they are listed
+fn rank_companions(items: Vec<HxDocItem>, present: &[String]) -> Vec<HxDocItem> {
+    let companions: Vec<&str> = present
+        .iter()
+        .filter_map(|name| COMPANIONS.iter().find(|(attribute, _)| attribute == name))
+        .flat_map(|(_, companions)| companions.iter().copied())
+        .collect();
+    let rank = |item: &HxDocItem| {
+        companions
+            .iter()
+            .position(|companion| *companion == item.name)
+            .unwrap_or(companions.len())
+    };
+
+    let mut items = items;
+    items.sort_by_key(rank);
+    items
+}
+
+/// The built in attributes followed by the ones the project defines
+fn attribute_items(custom: &CustomData) -> Vec<HxDocItem> {
+    HX_TAGS
+        .iter()
+        .cloned()
+        .chain(
+            custom
+                .attributes
+                .iter()
+                .filter(|attribute| !HX_TAGS.iter().any(|tag| tag.name == attribute.name))
+                .map(|attribute| HxDocItem {
+                    name: Cow::Owned(attribute.name.clone()),
+                    desc: Cow::Owned(attribute.description.clone()),
+                }),
+        )
+        .collect()
+}
+
+/// The built in values of an attribute followed by the ones the project defines
+fn attribute_value_items(custom: &CustomData, name: &str) -> Vec<HxDocItem> {
+    let builtin = HX_ATTRIBUTE_VALUES.get(name).copied().unwrap_or_default();
+    let values = custom
+        .attribute(name)
+        .map(|attribute| attribute.values.as_slice())
+        .unwrap_or_default();
+
+    builtin
+        .iter()
+        .cloned()
+        .chain(
+            values
+                .iter()
+                .filter(|value| !builtin.iter().any(|item| item.name == value.name))
+                .map(|value| HxDocItem {
+                    name: Cow::Owned(value.name.clone()),
+                    desc: Cow::Owned(value.description.clone()),
+                }),
+        )
+        .collect()
+}
+
+/// Attribute items spelled with the prefix the user started typing, so that
+/// accepting `hx-get` after `data-hx-` keeps the `data-` form
+fn prefixed_items(items: &[HxDocItem], prefix: &str) -> Vec<HxDocItem> {
+    items
+        .iter()
+        .map(|item| HxDocItem {
+            name: match prefix {
+                CANONICAL_PREFIX => item.name.clone(),
+                prefix => Cow::Owned(with_prefix(prefix, &item.name)),
+            },
+            desc: item.desc.clone(),
+        })
+        .collect()
+}
+
+/// Every extension `hx-ext` accepts, from the catalog, the settings and the
+/// project's custom data
+fn all_extension_items(custom: &CustomData) -> Vec<HxDocItem> {
+    let extensions: Vec<_> = config()
+        .extensions
+        .iter()
+        .chain(&custom.extensions)
+        .cloned()
+        .collect();
+
+    extension_items(&extensions)
+}
+
+/// The built in extensions followed by the ones defined by the project
+fn extension_items(extensions: &[ExtensionDefinition]) -> Vec<HxDocItem> {
+    let builtin = HX_ATTRIBUTE_VALUES
+        .get("hx-ext")
+        .copied()
+        .unwrap_or_default();
+
+    builtin
+        .iter()
+        .cloned()
+        .chain(
+            extensions
+                .iter()
+                .filter(|ext| !builtin.iter().any(|item| item.name == ext.name))
+                .map(|ext| HxDocItem {
+                    name: Cow::Owned(ext.name.clone()),
+                    desc: Cow::Owned(ext.description.clone()),
+                }),
+        )
+        .collect()
+}
+
+fn hx_trigger_completion(text_params: TextDocumentPositionParams) -> Option<&'static [HxDocItem]> {
+    // Unterminated values are not parsed into an attribute, offer everything
+    let (value, offset) = match get_attribute_value_at_position(text_params) {
+        Some((attribute, offset)) => (attribute.value?.value, offset),
+        None => return HX_ATTRIBUTE_VALUES.get("hx-trigger").copied(),
+    };
+
+    match trigger_completion(&value, offset)? {
+        TriggerCompletion::Event => Some(HX_TRIGGER_EVENTS),
+        TriggerCompletion::Modifier => Some(HX_TRIGGER_MODIFIERS),
+        TriggerCompletion::Interval => Some(HX_TRIGGER_INTERVALS),
+        TriggerCompletion::From => Some(HX_TRIGGER_FROM),
+        TriggerCompletion::Queue => Some(HX_TRIGGER_QUEUE),
+    }
+}
+
+/// Keywords where a selector starts, the ids of the document after `#`, and
+/// what goes after the colon of hx-sync and hx-select-oob
+fn hx_selector_completion(
+    text_params: &TextDocumentPositionParams,
+    name: &str,
+    custom: &CustomData,
+) -> Option<Vec<HxDocItem>> {
+    let items = attribute_value_items(custom, name);
+
+    // Unterminated values are not parsed into an attribute, offer everything
+    let (value, offset) = match get_attribute_value_at_position(text_params.clone()) {
+        Some((attribute, offset)) => (attribute.value?.value, offset),
+        None => return Some(items),
+    };
+
+    match selector_completion(name, &value, offset)? {
+        // The hx-sync values are its strategies
+        SelectorCompletion::Prefix if name == "hx-sync" => None,
+        SelectorCompletion::Prefix => Some(items),
+        SelectorCompletion::Strategy if name == "hx-select-oob" => HX_ATTRIBUTE_VALUES
+            .get("hx-swap")
+            .map(|items| items.to_vec()),
+        SelectorCompletion::Strategy => Some(items),
+        SelectorCompletion::Id => {
+            let document = get_document(&text_params.text_document.uri)?;
+            let mut ids: Vec<_> = document_ids(&document)
+                .into_iter()
+                .map(|element| element.id)
+                .filter(|id| !id.is_empty() && !is_templated(id))
+                .collect();
+            ids.sort();
+            ids.dedup();
+
+            Some(
+                ids.into_iter()
+                    .map(|id| HxDocItem {
+                        name: Cow::Owned(id),
+                        desc: Cow::Borrowed(""),
+                    })
+                    .collect(),
+            )
+        }
     }
 }
 
 pub fn hx_hover(text_params: TextDocumentPositionParams) -> Option<HxDocItem> {
+    let version = htmx_version(Some(text_params.text_document.uri.as_str()));
+
+    if let Some((attribute, offset)) = get_attribute_value_at_position(text_params.clone()) {
+        if let Some(item) = hx_value_hover(&attribute, offset, version) {
+            return Some(item);
+        }
+    }
+
+    // Event names are not part of the word under the cursor, which stops at `:`
+    if let Some(attribute) = get_attribute_name_at_position(text_params.clone()) {
+        if let Some(item) = hx_on_event(&attribute.name) {
+            return Some(item);
+        }
+    }
+
     let result = match get_word_from_pos_params(&text_params) {
         Ok(word) => Position::AttributeName(word),
         Err(_) => {
@@ -43,11 +510,135 @@ pub fn hx_hover(text_params: TextDocumentPositionParams) -> Option<HxDocItem> {
 
     match result {
         Position::AttributeName(name) | Position::AttributeValue { name, .. } => {
-            HX_TAGS.iter().find(|x| x.name == name).cloned()
+            let name = canonical_name(&name)?;
+            let item = attribute_items(&custom_data())
+                .into_iter()
+                .find(|x| x.name == name)?;
+
+            Some(with_availability_note(item, &name, None, version))
         }
     }
 }
 
+/// Documentation of the value under the cursor, such as a swap style, a
+/// trigger event or modifier, or an extension name
+fn hx_value_hover(
+    attribute: &HtmxAttribute,
+    offset: usize,
+    version: Option<HtmxVersion>,
+) -> Option<HxDocItem> {
+    let value = attribute.value.as_ref()?.value.as_str();
+    let contains = |token: &Token<'_>| (token.start..=token.end()).contains(&offset);
+    let find = |items: &[HxDocItem], word: &str| items.iter().find(|x| x.name == word).cloned();
+
+    let (word, item) = match attribute.name.as_str() {
+        "hx-trigger" => {
+            let (kind, word) = trigger_word_at(value, offset)?;
+            let item = match kind {
+                TriggerCompletion::Event => find(HX_TRIGGER_EVENTS, word.text),
+                TriggerCompletion::Modifier => find(HX_TRIGGER_MODIFIERS, word.text),
+                // Every interval shares the same documentation
+                TriggerCompletion::Interval => HX_TRIGGER_INTERVALS.first().cloned(),
+                TriggerCompletion::From => find(HX_TRIGGER_FROM, word.text),
+                TriggerCompletion::Queue => find(HX_TRIGGER_QUEUE, word.text),
+            };
+            (word, item?)
+        }
+        "hx-ext" => {
+            let word = split_list(value).into_iter().find(contains)?;
+            (word, find(&all_extension_items(&custom_data()), word.text)?)
+        }
+        name => {
+            let word = tokenize(value).into_iter().find(contains)?;
+            // Only the style of an hx-swap value has documentation
+            if name == "hx-swap" && word.start != tokenize(value).first()?.start {
+                return None;
+            }
+            (
+                word,
+                find(&attribute_value_items(&custom_data(), name), word.text)?,
+            )
+        }
+    };
+
+    let item = HxDocItem {
+        name: item.name,
+        desc: Cow::Owned(format!("{} {}\n\n{}", attribute.name, word.text, item.desc)),
+    };
+
+    Some(with_availability_note(
+        item,
+        &attribute.name,
+        Some(word.text),
+        version,
+    ))
+}
+
+/// Prepends why an attribute or value does not fit the project's htmx version
+fn with_availability_note(
+    item: HxDocItem,
+    attribute: &str,
+    value: Option<&str>,
+    version: Option<HtmxVersion>,
+) -> HxDocItem {
+    let note = match availability(attribute, value, version) {
+        Availability::Available => return item,
+        Availability::Deprecated(note)
+        | Availability::Removed(note)
+        | Availability::NotYetAdded(note) => note,
+    };
+
+    HxDocItem {
+        name: item.name,
+        desc: Cow::Owned(format!("**{note}**\n\n{}", item.desc)),
+    }
+}
+
+/// The selectors of an attribute value, including those of the hx-trigger
+/// modifiers that take one
+fn value_selectors<'a>(attribute: &str, value: &'a str) -> Vec<ExtendedSelector<'a>> {
+    match attribute {
+        "hx-trigger" => parse_trigger(value)
+            .specs
+            .into_iter()
+            .flat_map(|spec| spec.modifiers)
+            .filter_map(|modifier| modifier.selector)
+            .collect(),
+        _ => parse_selector_attribute(attribute, value)
+            .map(|selectors| selectors.selectors)
+            .unwrap_or_default(),
+    }
+}
+
+/// Resolves the `#id` under the cursor to the element declaring it, looking in
+/// the current document before the rest of the workspace
+pub fn hx_definition(text_params: TextDocumentPositionParams) -> Option<Vec<Location>> {
+    let uri = text_params.text_document.uri.clone();
+    let (attribute, offset) = get_attribute_value_at_position(text_params)?;
+    let name = attribute.name.as_str();
+    let value = attribute.value.as_ref()?;
+
+    let id = selector_id_at(&value_selectors(name, &value.value), offset)?;
+    debug!("hx_definition looking for id: {:?}", id);
+
+    let document = get_document(&uri)?;
+    let local: Vec<_> = document_ids(&document)
+        .into_iter()
+        .filter(|element| element.id == id.text)
+        .map(|element| Location {
+            uri: uri.clone(),
+            range: element.range,
+        })
+        .collect();
+
+    if !local.is_empty() {
+        return Some(local);
+    }
+
+    let workspace = find_id_in_workspace(id.text, uri.as_str());
+    (!workspace.is_empty()).then_some(workspace)
+}
+
 pub static HX_TAGS: &[HxDocItem] = build_completion!(
     ("hx-boost", "./attributes/hx-boost.md"),
     ("hx-delete", "./attributes/hx-delete.md"),
@@ -80,7 +671,139 @@ pub static HX_TAGS: &[HxDocItem] = build_completion!(
     ("hx-replace-url", "./attributes/hx-replace-url.md"),
     ("hx-request", "./attributes/hx-request.md"),
     ("hx-sync", "./attributes/hx-sync.md"),
-    ("hx-validate", "./attributes/hx-validate.md")
+    ("hx-validate", "./attributes/hx-validate.md"),
+    ("hx-disinherit", "./attributes/hx-disinherit.md"),
+    ("hx-inherit", "./attributes/hx-inherit.md"),
+    ("hx-vars", "./attributes/hx-vars.md"),
+    ("hx-sse", "./attributes/hx-sse.md"),
+    ("hx-ws", "./attributes/hx-ws.md")
+);
+
+pub static HX_TRIGGER_EVENTS: &[HxDocItem] = build_completion!(
+    ("click", "./hx-trigger/click.md"),
+    ("keyup", "./hx-trigger/keyup.md"),
+    ("load", "./hx-trigger/load.md"),
+    ("revealed", "./hx-trigger/revealed.md"),
+    ("intersect", "./hx-trigger/intersect.md"),
+    ("every", "./hx-trigger/every.md")
+);
+
+pub static HX_TRIGGER_MODIFIERS: &[HxDocItem] = build_completion!(
+    ("once", "./hx-trigger/once.md"),
+    ("changed", "./hx-trigger/changed.md"),
+    ("delay:", "./hx-trigger/delay.md"),
+    ("throttle:", "./hx-trigger/throttle.md"),
+    ("from:", "./hx-trigger/from.md"),
+    ("target:", "./hx-trigger/target.md"),
+    ("consume", "./hx-trigger/consume.md"),
+    ("queue:", "./hx-trigger/queue.md")
+);
+
+pub static HX_TRIGGER_INTERVALS: &[HxDocItem] = build_completion!(
+    ("100ms", "./hx-trigger/interval.md"),
+    ("500ms", "./hx-trigger/interval.md"),
+    ("1s", "./hx-trigger/interval.md"),
+    ("2s", "./hx-trigger/interval.md"),
+    ("1m", "./hx-trigger/interval.md")
+);
+
+pub static HX_TRIGGER_FROM: &[HxDocItem] = build_completion!(
+    ("document", "./hx-trigger/document.md"),
+    ("window", "./hx-trigger/window.md"),
+    ("closest", "./hx-target/closest.md"),
+    ("find", "./hx-target/find.md"),
+    ("next", "./hx-target/next.md"),
+    ("previous", "./hx-target/previous.md")
+);
+
+pub static HX_TRIGGER_QUEUE: &[HxDocItem] = build_completion!(
+    ("first", "./hx-trigger/queue-first.md"),
+    ("last", "./hx-trigger/queue-last.md"),
+    ("all", "./hx-trigger/queue-all.md"),
+    ("none", "./hx-trigger/queue-none.md")
+);
+
+/// htmx events as written after `hx-on::`, in kebab-case since attribute
+/// names are not case sensitive
+pub static HX_EVENTS: &[HxDocItem] = build_completion!(
+    ("abort", "./hx-on/abort.md"),
+    ("after-on-load", "./hx-on/after-on-load.md"),
+    ("after-process-node", "./hx-on/after-process-node.md"),
+    ("after-request", "./hx-on/after-request.md"),
+    ("after-settle", "./hx-on/after-settle.md"),
+    ("after-swap", "./hx-on/after-swap.md"),
+    (
+        "before-cleanup-element",
+        "./hx-on/before-cleanup-element.md"
+    ),
+    ("before-history-save", "./hx-on/before-history-save.md"),
+    ("before-on-load", "./hx-on/before-on-load.md"),
+    ("before-process-node", "./hx-on/before-process-node.md"),
+    ("before-request", "./hx-on/before-request.md"),
+    ("before-send", "./hx-on/before-send.md"),
+    ("before-swap", "./hx-on/before-swap.md"),
+    ("before-transition", "./hx-on/before-transition.md"),
+    ("config-request", "./hx-on/config-request.md"),
+    ("confirm", "./hx-on/confirm.md"),
+    ("history-cache-error", "./hx-on/history-cache-error.md"),
+    (
+        "history-cache-miss-error",
+        "./hx-on/history-cache-miss-error.md"
+    ),
+    (
+        "history-cache-miss-load",
+        "./hx-on/history-cache-miss-load.md"
+    ),
+    ("history-cache-miss", "./hx-on/history-cache-miss.md"),
+    ("history-restore", "./hx-on/history-restore.md"),
+    ("load", "./hx-on/load.md"),
+    ("oob-after-swap", "./hx-on/oob-after-swap.md"),
+    ("oob-before-swap", "./hx-on/oob-before-swap.md"),
+    ("oob-error-no-target", "./hx-on/oob-error-no-target.md"),
+    ("prompt", "./hx-on/prompt.md"),
+    ("pushed-into-history", "./hx-on/pushed-into-history.md"),
+    ("replaced-in-history", "./hx-on/replaced-in-history.md"),
+    ("response-error", "./hx-on/response-error.md"),
+    ("send-abort", "./hx-on/send-abort.md"),
+    ("send-error", "./hx-on/send-error.md"),
+    ("swap-error", "./hx-on/swap-error.md"),
+    ("target-error", "./hx-on/target-error.md"),
+    ("timeout", "./hx-on/timeout.md"),
+    ("trigger", "./hx-on/trigger.md"),
+    ("validate-url", "./hx-on/validate-url.md"),
+    ("validation:failed", "./hx-on/validation-failed.md"),
+    ("validation:halted", "./hx-on/validation-halted.md"),
+    ("validation:validate", "./hx-on/validation-validate.md"),
+    ("xhr:abort", "./hx-on/xhr-abort.md"),
+    ("xhr:loadend", "./hx-on/xhr-loadend.md"),
+    ("xhr:loadstart", "./hx-on/xhr-loadstart.md"),
+    ("xhr:progress", "./hx-on/xhr-progress.md")
+);
+
+/// Common DOM events as written after `hx-on:`
+pub static DOM_EVENTS: &[HxDocItem] = build_completion!(
+    ("blur", "./dom-events/blur.md"),
+    ("change", "./dom-events/change.md"),
+    ("click", "./dom-events/click.md"),
+    ("contextmenu", "./dom-events/contextmenu.md"),
+    ("dblclick", "./dom-events/dblclick.md"),
+    ("focus", "./dom-events/focus.md"),
+    ("focusin", "./dom-events/focusin.md"),
+    ("focusout", "./dom-events/focusout.md"),
+    ("input", "./dom-events/input.md"),
+    ("keydown", "./dom-events/keydown.md"),
+    ("keyup", "./dom-events/keyup.md"),
+    ("load", "./dom-events/load.md"),
+    ("mousedown", "./dom-events/mousedown.md"),
+    ("mouseenter", "./dom-events/mouseenter.md"),
+    ("mouseleave", "./dom-events/mouseleave.md"),
+    ("mouseover", "./dom-events/mouseover.md"),
+    ("mouseup", "./dom-events/mouseup.md"),
+    ("reset", "./dom-events/reset.md"),
+    ("scroll", "./dom-events/scroll.md"),
+    ("submit", "./dom-events/submit.md"),
+    ("touchend", "./dom-events/touchend.md"),
+    ("touchstart", "./dom-events/touchstart.md")
 );
 
 pub static HX_ATTRIBUTE_VALUES: phf::Map<&'static str, &[HxDocItem]> = phf::phf_map! {
@@ -93,14 +816,15 @@ pub static HX_ATTRIBUTE_VALUES: phf::Map<&'static str, &[HxDocItem]> = phf::phf_
         ("beforebegin", "./hx-swap/beforebegin.md"),
         ("beforeend", "./hx-swap/beforeend.md"),
         ("delete", "./hx-swap/delete.md"),
-        ("none", "./hx-swap/none.md")
+        ("none", "./hx-swap/none.md"),
+        ("textContent", "./hx-swap/textContent.md")
     ] as &[_],
 
     "hx-target" => build_completion![
         ("closest", "./hx-target/closest.md"),
         ("find", "./hx-target/find.md"),
         ("next", "./hx-target/next.md"),
-        ("prev", "./hx-target/prev.md"),
+        ("previous", "./hx-target/previous.md"),
         ("this", "./hx-target/this.md")
     ] as &[_],
 
@@ -195,3 +919,24 @@ pub static HX_ATTRIBUTE_VALUES: phf::Map<&'static str, &[HxDocItem]> = phf::phf_
         ("queue", "./hx-sync/queue.md")
     ] as &[_]
 };
+
+#[cfg(test)]
+mod tests {
+    use super::{attribute_snippet, escape_choice};
+
+    #[test]
+    fn test_attribute_snippets_offer_known_values_as_choices() {
+        assert_eq!(attribute_snippet("hx-get", None), r#"hx-get="$1""#);
+        assert_eq!(attribute_snippet("hx-trigger", None), r#"hx-trigger="$1""#);
+        assert_eq!(attribute_snippet("hx-target", None), r#"hx-target="$1""#);
+        assert_eq!(attribute_snippet("hx-ext", None), r#"hx-ext="$1""#);
+        assert_eq!(
+            attribute_snippet("data-hx-boost", None),
+            r#"data-hx-boost="${1|true,false|}""#
+        );
+        assert!(
+            attribute_snippet("hx-swap", None).starts_with(r#"hx-swap="${1|innerHTML,outerHTML,"#)
+        );
+        assert_eq!(escape_choice("a,b|c"), r"a\,b\|c");
+    }
+}
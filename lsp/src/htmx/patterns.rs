@@ -0,0 +1,157 @@
+// See: https://htmx.org/examples/
+
+use crate::config::PatternDefinition;
+
+use super::prefix::CANONICAL_PREFIX;
+
+/// A recipe of several attributes, inserted as one snippet inside a start tag
+pub struct Pattern {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Snippet syntax, written with `hx-` attributes
+    pub body: &'static str,
+}
+
+pub static PATTERNS: &[Pattern] = &[
+    Pattern {
+        name: "active-search",
+        description: "Search as the user types, once they pause for half a second",
+        body: r#"hx-post="${1:/search}" hx-trigger="keyup changed delay:500ms" hx-target="${2:#search-results}""#,
+    },
+    Pattern {
+        name: "infinite-scroll",
+        description: "Load the next page once the last element scrolls into view",
+        body: r#"hx-get="${1:/items?page=2}" hx-trigger="revealed" hx-swap="afterend""#,
+    },
+    Pattern {
+        name: "click-to-edit",
+        description: "Replace the element with its edit form when clicked",
+        body: r#"hx-get="${1:/contact/1/edit}" hx-target="this" hx-swap="outerHTML""#,
+    },
+    Pattern {
+        name: "lazy-load",
+        description: "Load expensive content once the page has loaded",
+        body: r#"hx-get="${1:/graph}" hx-trigger="load""#,
+    },
+    Pattern {
+        name: "delete-row",
+        description: "Delete the row after confirming, fading it out",
+        body: r#"hx-delete="${1:/contacts/1}" hx-confirm="${2:Are you sure?}" hx-target="closest tr" hx-swap="outerHTML swap:1s""#,
+    },
+    Pattern {
+        name: "polling",
+        description: "Refresh the element at a fixed interval",
+        body: r#"hx-get="${1:/news}" hx-trigger="every ${2:2s}""#,
+    },
+];
+
+/// The built in patterns followed by the ones defined in the configuration,
+/// which replace built in patterns of the same name
+pub fn pattern_definitions(configured: &[PatternDefinition]) -> Vec<PatternDefinition> {
+    PATTERNS
+        .iter()
+        .filter(|pattern| !configured.iter().any(|c| c.name == pattern.name))
+        .map(|pattern| PatternDefinition {
+            name: pattern.name.to_string(),
+            description: pattern.description.to_string(),
+            body: pattern.body.to_string(),
+        })
+        .chain(configured.iter().cloned())
+        .collect()
+}
+
+/// A pattern as offered to a user typing attributes with `prefix`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternCompletion {
+    pub label: String,
+    /// Starts with the prefix so that clients match it against what is typed
+    pub filter_text: String,
+    pub description: String,
+    pub body: String,
+}
+
+pub fn pattern_completions(
+    configured: &[PatternDefinition],
+    prefix: &str,
+) -> Vec<PatternCompletion> {
+    pattern_definitions(configured)
+        .into_iter()
+        .map(|pattern| PatternCompletion {
+            filter_text: format!("{prefix}{}", pattern.name),
+            body: with_body_prefix(&pattern.body, prefix),
+            label: pattern.name,
+            description: pattern.description,
+        })
+        .collect()
+}
+
+/// Rewrites the `hx-` attributes of a pattern body with the prefix the user
+/// is typing, e.g. `data-hx-`
+pub fn with_body_prefix(body: &str, prefix: &str) -> String {
+    if prefix == CANONICAL_PREFIX {
+        return body.to_string();
+    }
+
+    let mut result = String::with_capacity(body.len());
+    let mut previous = None;
+    let mut rest = body;
+
+    while let Some(c) = rest.chars().next() {
+        let at_attribute = previous.is_none_or(char::is_whitespace);
+        match rest.strip_prefix(CANONICAL_PREFIX) {
+            Some(after) if at_attribute => {
+                result.push_str(prefix);
+                previous = CANONICAL_PREFIX.chars().last();
+                rest = after;
+            }
+            _ => {
+                result.push(c);
+                previous = Some(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pattern_definitions, with_body_prefix};
+    use crate::config::PatternDefinition;
+
+    #[test]
+    fn test_configured_patterns_extend_and_replace_builtins() {
+        let configured = vec![
+            PatternDefinition {
+                name: "polling".to_string(),
+                description: "Poll slowly".to_string(),
+                body: r#"hx-get="$1" hx-trigger="every 1m""#.to_string(),
+            },
+            PatternDefinition {
+                name: "house-modal".to_string(),
+                description: String::new(),
+                body: r##"hx-get="$1" hx-target="#modal""##.to_string(),
+            },
+        ];
+
+        let patterns = pattern_definitions(&configured);
+        let polling: Vec<_> = patterns.iter().filter(|p| p.name == "polling").collect();
+
+        assert_eq!(polling.len(), 1);
+        assert_eq!(polling[0].description, "Poll slowly");
+        assert!(patterns.iter().any(|p| p.name == "house-modal"));
+        assert!(patterns.iter().any(|p| p.name == "active-search"));
+    }
+
+    #[test]
+    fn test_it_rewrites_the_attribute_prefix() {
+        let body = r#"hx-get="/a-hx-b" hx-trigger="load""#;
+
+        assert_eq!(with_body_prefix(body, "hx-"), body);
+        assert_eq!(
+            with_body_prefix(body, "data-hx-"),
+            r#"data-hx-get="/a-hx-b" data-hx-trigger="load""#
+        );
+    }
+}
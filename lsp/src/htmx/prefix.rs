@@ -0,0 +1,127 @@
+// See: https://htmx.org/docs/#attributes
+
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use log::warn;
+
+/// The prefix every attribute in the catalog is written with
+pub const CANONICAL_PREFIX: &str = "hx-";
+
+/// htmx reads every attribute with a `data-` prefix as well, which keeps HTML
+/// validators happy
+pub const BUILTIN_PREFIXES: &[&str] = &["data-hx-", CANONICAL_PREFIX];
+
+/// The built in and configured prefixes, longest first so that a prefix never
+/// shadows a longer one that contains it
+fn sorted_prefixes(configured: &[String]) -> Vec<String> {
+    let mut prefixes: Vec<String> = configured
+        .iter()
+        .filter(|prefix| {
+            // Prefixes end up in a query regex, keep them to plain names
+            let valid = !prefix.is_empty()
+                && prefix
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'));
+            if !valid {
+                warn!("ignoring invalid attribute prefix {prefix:?}");
+            }
+            valid
+        })
+        .cloned()
+        .chain(BUILTIN_PREFIXES.iter().map(|prefix| prefix.to_string()))
+        .collect();
+
+    prefixes.sort_by_key(|prefix| std::cmp::Reverse(prefix.len()));
+    prefixes.dedup();
+    prefixes
+}
+
+fn lock_prefixes() -> MutexGuard<'static, Arc<Vec<String>>> {
+    static PREFIXES: OnceLock<Mutex<Arc<Vec<String>>>> = OnceLock::new();
+
+    PREFIXES
+        .get_or_init(|| Mutex::new(Arc::new(sorted_prefixes(&[]))))
+        .lock()
+        .expect("attribute prefixes mutex poisoned")
+}
+
+/// Works out the prefixes of new settings, once instead of on every lookup
+pub fn set_attribute_prefixes(configured: &[String]) {
+    *lock_prefixes() = Arc::new(sorted_prefixes(configured));
+}
+
+/// The prefixes attributes may be written with, longest first
+pub fn attribute_prefixes() -> Arc<Vec<String>> {
+    lock_prefixes().clone()
+}
+
+/// A tree-sitter `#match?` pattern for attribute names starting with any of
+/// the prefixes
+pub fn prefix_pattern(prefixes: &[String]) -> String {
+    format!("^({})", prefixes.join("|"))
+}
+
+/// Splits an attribute name into the prefix it is written with and the
+/// canonical `hx-` name the catalog knows it by, e.g. `data-hx-get` into
+/// `data-hx-` and `hx-get`
+pub fn split_prefix<'a>(name: &'a str, prefixes: &[String]) -> Option<(&'a str, String)> {
+    prefixes.iter().find_map(|prefix| {
+        let rest = name.strip_prefix(prefix.as_str())?;
+        Some((&name[..prefix.len()], format!("{CANONICAL_PREFIX}{rest}")))
+    })
+}
+
+/// The canonical `hx-` name of an attribute written with any prefix
+pub fn canonical_name(name: &str) -> Option<String> {
+    split_prefix(name, &attribute_prefixes()).map(|(_, canonical)| canonical)
+}
+
+/// Writes a canonical `hx-` name with the prefix the user chose
+pub fn with_prefix(prefix: &str, canonical: &str) -> String {
+    format!(
+        "{prefix}{}",
+        canonical
+            .strip_prefix(CANONICAL_PREFIX)
+            .unwrap_or(canonical)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prefix_pattern, sorted_prefixes, split_prefix, with_prefix};
+
+    fn prefixes() -> Vec<String> {
+        ["data-hx-", "hx-"].iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_split_prefix() {
+        assert_eq!(
+            split_prefix("data-hx-get", &prefixes()),
+            Some(("data-hx-", "hx-get".to_string()))
+        );
+        assert_eq!(
+            split_prefix("hx-on::after-request", &prefixes()),
+            Some(("hx-", "hx-on::after-request".to_string()))
+        );
+        assert_eq!(split_prefix("data-id", &prefixes()), None);
+        assert_eq!(split_prefix("class", &prefixes()), None);
+    }
+
+    #[test]
+    fn test_with_prefix_and_pattern() {
+        assert_eq!(with_prefix("data-hx-", "hx-target"), "data-hx-target");
+        assert_eq!(with_prefix("hx-", "hx-target"), "hx-target");
+        assert_eq!(prefix_pattern(&prefixes()), "^(data-hx-|hx-)");
+    }
+
+    #[test]
+    fn test_sorted_prefixes() {
+        let configured = ["x-", "hx-", "bad prefix", "x-data-hx-"].map(String::from);
+
+        assert_eq!(
+            sorted_prefixes(&configured),
+            vec!["x-data-hx-", "data-hx-", "hx-", "x-"]
+        );
+    }
+}
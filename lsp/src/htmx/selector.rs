@@ -0,0 +1,932 @@
+// See: https://htmx.org/docs/#extended-css-selectors
+
+use super::{
+    swap::swap_styles,
+    syntax::{tokenize, SyntaxError, Token},
+};
+
+/// Attributes whose value is a (possibly extended) CSS selector that can point
+/// at an element by id
+pub const ID_REFERENCE_ATTRIBUTES: &[&str] = &[
+    "hx-target",
+    "hx-include",
+    "hx-indicator",
+    "hx-disabled-elt",
+    "hx-select",
+    "hx-swap-oob",
+];
+
+/// htmx keywords an extended selector can start with
+pub const EXTENDED_PREFIXES: &[&str] = &[
+    "closest", "find", "next", "previous", "this", "document", "window",
+];
+
+/// Attributes whose value is read as selectors
+pub const SELECTOR_ATTRIBUTES: &[&str] = &[
+    "hx-target",
+    "hx-select",
+    "hx-select-oob",
+    "hx-include",
+    "hx-indicator",
+    "hx-disabled-elt",
+    "hx-sync",
+];
+
+/// Keywords that stand for an element on their own
+const STANDALONE_PREFIXES: &[&str] = &["this", "document", "window"];
+
+/// Keywords that only make sense with a selector after them
+const SELECTOR_PREFIXES: &[&str] = &["closest", "find"];
+
+const SYNC_STRATEGIES: &[&str] = &["drop", "abort", "replace", "queue"];
+const QUEUE_STRATEGIES: &[&str] = &["first", "last", "all"];
+
+/// What template engines start an interpolation with. A single `{` is left
+/// out since JSON values are full of them.
+const TEMPLATE_OPENERS: &[&str] = &["{{", "{%", "{#", "${", "<%", "<?", "@"];
+
+/// Characters template engines use to interpolate values, e.g. `row-{{ id }}`
+const TEMPLATE_MARKERS: &[char] = &['{', '}', '$', '%', '<', '>', '@', '|'];
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimpleSelectorKind {
+    Type,
+    Universal,
+    Id,
+    Class,
+    Attribute,
+    PseudoClass,
+    PseudoElement,
+}
+
+/// One piece of a compound selector. Ids, classes and pseudo selectors are
+/// kept without their `#`, `.` or `:` marker, attribute selectors with their
+/// brackets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimpleSelector<'a> {
+    pub kind: SimpleSelectorKind,
+    pub token: Token<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedSelector<'a> {
+    /// The htmx keyword the selector starts with, e.g. `closest`
+    pub prefix: Option<Token<'a>>,
+    /// The CSS part, absent for `this` or a bare `next`
+    pub css: Option<Token<'a>>,
+    pub parts: Vec<SimpleSelector<'a>>,
+}
+
+impl<'a> ExtendedSelector<'a> {
+    /// The ids the selector refers to, without the leading `#`
+    pub fn ids(&self) -> impl Iterator<Item = Token<'a>> + '_ {
+        self.parts
+            .iter()
+            .filter(|part| part.kind == SimpleSelectorKind::Id)
+            .map(|part| part.token)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selectors<'a> {
+    pub selectors: Vec<ExtendedSelector<'a>>,
+    pub errors: Vec<SyntaxError>,
+}
+
+/// What can be typed at a given offset of a selector-typed value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectorCompletion {
+    /// The first word of a selector, where htmx keywords go
+    Prefix,
+    /// The name right after a `#`
+    Id,
+    /// What follows the selector after a `:` in hx-sync and hx-select-oob
+    Strategy,
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || matches!(c, '-' | '_' | '\\') || !c.is_ascii()
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii()
+}
+
+/// Whether a template engine builds the value, e.g. `#row-{{ id }}`, which
+/// leaves nothing to check until it is rendered
+pub fn is_templated(value: &str) -> bool {
+    TEMPLATE_OPENERS.iter().any(|opener| value.contains(opener))
+}
+
+fn trim(token: Token<'_>) -> Token<'_> {
+    let trimmed = token.text.trim_start();
+
+    Token {
+        text: trimmed.trim_end(),
+        start: token.start + token.text.len() - trimmed.len(),
+    }
+}
+
+/// Splits on `separator` outside of brackets, parentheses and strings,
+/// keeping empty items around so they can be reported
+fn split_top_level(value: Token<'_>, separator: char) -> Vec<Token<'_>> {
+    let mut items = vec![];
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut item_start = 0;
+
+    for (idx, c) in value.text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[' | '(') => depth += 1,
+            (None, ']' | ')') => depth = depth.saturating_sub(1),
+            (None, c) if c == separator && depth == 0 => {
+                items.push(Token {
+                    text: &value.text[item_start..idx],
+                    start: value.start + item_start,
+                });
+                item_start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+
+    items.push(Token {
+        text: &value.text[item_start..],
+        start: value.start + item_start,
+    });
+
+    items
+}
+
+/// Splits `selector:rest` on its first colon, the way htmx reads hx-sync and
+/// hx-select-oob. Pseudo-classes can't be used there.
+fn split_first_colon(value: Token<'_>) -> (Token<'_>, Option<Token<'_>>) {
+    match value.text.split_once(':') {
+        Some((selector, rest)) => (
+            Token {
+                text: selector,
+                start: value.start,
+            },
+            Some(Token {
+                text: rest,
+                start: value.start + selector.len() + 1,
+            }),
+        ),
+        None => (value, None),
+    }
+}
+
+/// A recursive descent parser for the selectors `querySelectorAll` accepts.
+/// It stops at the first error, since browsers reject the whole selector.
+struct CssParser<'a> {
+    text: &'a str,
+    /// Offset of `text` in the attribute value
+    base: usize,
+    pos: usize,
+    parts: Vec<SimpleSelector<'a>>,
+}
+
+impl<'a> CssParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+        self.pos > start
+    }
+
+    fn token(&self, start: usize, end: usize) -> Token<'a> {
+        Token {
+            text: &self.text[start..end],
+            start: self.base + start,
+        }
+    }
+
+    fn error(&self, start: usize, end: usize, message: impl Into<String>) -> SyntaxError {
+        SyntaxError::new(self.base + start..self.base + end, message)
+    }
+
+    fn unexpected(&self) -> SyntaxError {
+        match self.peek() {
+            Some(c) => self.error(
+                self.pos,
+                self.pos + c.len_utf8(),
+                format!("Unexpected `{c}` in CSS selector"),
+            ),
+            None => self.error(self.pos, self.pos, "Unexpected end of CSS selector"),
+        }
+    }
+
+    fn unclosed(&self, start: usize, what: &str) -> SyntaxError {
+        self.error(start, start + 1, format!("Unclosed {what} in CSS selector"))
+    }
+
+    fn ident(&mut self) -> Token<'a> {
+        let start = self.pos;
+
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => {
+                    self.bump();
+                    self.bump();
+                }
+                c if is_ident_char(c) => {
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+
+        self.token(start, self.pos)
+    }
+
+    fn push(&mut self, kind: SimpleSelectorKind, token: Token<'a>) {
+        self.parts.push(SimpleSelector { kind, token });
+    }
+
+    /// `#id` or `.class`, with the cursor on the marker
+    fn name(&mut self, kind: SimpleSelectorKind, what: &str) -> Result<(), SyntaxError> {
+        let marker = self.pos;
+        self.bump();
+
+        let name = self.ident();
+        if name.text.is_empty() {
+            return Err(self.error(
+                marker,
+                marker + 1,
+                format!("Expected {what} after `{}`", &self.text[marker..=marker]),
+            ));
+        }
+        if name.text.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(self.error(
+                marker,
+                self.pos,
+                format!(
+                    "`{}` is not a valid CSS selector, a leading digit has to be escaped",
+                    &self.text[marker..self.pos]
+                ),
+            ));
+        }
+
+        self.push(kind, name);
+        Ok(())
+    }
+
+    /// Skips a quoted string, with the cursor on the opening quote
+    fn string(&mut self) -> Result<(), SyntaxError> {
+        let start = self.pos;
+        let quote = self.bump();
+
+        while let Some(c) = self.bump() {
+            match c {
+                '\\' => {
+                    self.bump();
+                }
+                c if Some(c) == quote => return Ok(()),
+                _ => {}
+            }
+        }
+
+        Err(self.unclosed(start, "string"))
+    }
+
+    /// Skips the arguments of a functional pseudo-class, with the cursor on
+    /// the opening parenthesis
+    fn arguments(&mut self) -> Result<(), SyntaxError> {
+        let start = self.pos;
+        let mut depth = 0usize;
+
+        while let Some(c) = self.peek() {
+            match c {
+                '"' | '\'' => {
+                    self.string()?;
+                    continue;
+                }
+                '\\' => {
+                    self.bump();
+                }
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.bump();
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+            self.bump();
+        }
+
+        Err(self.unclosed(start, "`(`"))
+    }
+
+    fn unclosed_or_unexpected(&self, start: usize) -> SyntaxError {
+        match self.peek() {
+            None => self.unclosed(start, "`[`"),
+            Some(_) => self.unexpected(),
+        }
+    }
+
+    /// `[name]`, `[name=value]` or `[name="value" i]`
+    fn attribute(&mut self) -> Result<(), SyntaxError> {
+        let start = self.pos;
+        self.bump();
+        self.skip_whitespace();
+
+        if self.ident().text.is_empty() {
+            return Err(match self.peek() {
+                None => self.unclosed(start, "`[`"),
+                Some(_) => self.error(start, start + 1, "Expected an attribute name after `[`"),
+            });
+        }
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(']') => {}
+            Some('=') => {
+                self.bump();
+                self.attribute_value(start)?;
+            }
+            Some('~' | '|' | '^' | '$' | '*') => {
+                self.bump();
+                if self.peek() != Some('=') {
+                    return Err(self.unclosed_or_unexpected(start));
+                }
+                self.bump();
+                self.attribute_value(start)?;
+            }
+            _ => return Err(self.unclosed_or_unexpected(start)),
+        }
+
+        if self.peek() != Some(']') {
+            return Err(self.unclosed_or_unexpected(start));
+        }
+        self.bump();
+
+        let token = self.token(start, self.pos);
+        self.push(SimpleSelectorKind::Attribute, token);
+        Ok(())
+    }
+
+    fn attribute_value(&mut self, start: usize) -> Result<(), SyntaxError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('"' | '\'') => self.string()?,
+            _ if self.ident().text.is_empty() => return Err(self.unclosed_or_unexpected(start)),
+            _ => {}
+        }
+
+        // The case sensitivity flag
+        if self.skip_whitespace() && matches!(self.peek(), Some('i' | 'I' | 's' | 'S')) {
+            self.bump();
+            self.skip_whitespace();
+        }
+
+        Ok(())
+    }
+
+    /// `:hover`, `:not(.done)` or `::before`
+    fn pseudo(&mut self) -> Result<(), SyntaxError> {
+        let marker = self.pos;
+        self.bump();
+
+        let kind = match self.peek() {
+            Some(':') => {
+                self.bump();
+                SimpleSelectorKind::PseudoElement
+            }
+            _ => SimpleSelectorKind::PseudoClass,
+        };
+
+        let name = self.ident();
+        if name.text.is_empty() {
+            return Err(self.error(
+                marker,
+                self.pos,
+                format!("Expected a name after `{}`", &self.text[marker..self.pos]),
+            ));
+        }
+        if self.peek() == Some('(') {
+            self.arguments()?;
+        }
+
+        self.push(kind, name);
+        Ok(())
+    }
+
+    fn compound(&mut self) -> Result<(), SyntaxError> {
+        let start = self.pos;
+
+        match self.peek() {
+            Some('*') => {
+                self.bump();
+                let token = self.token(start, self.pos);
+                self.push(SimpleSelectorKind::Universal, token);
+            }
+            Some(c) if is_ident_start(c) => {
+                let token = self.ident();
+                self.push(SimpleSelectorKind::Type, token);
+            }
+            _ => {}
+        }
+
+        loop {
+            match self.peek() {
+                Some('#') => self.name(SimpleSelectorKind::Id, "an id")?,
+                Some('.') => self.name(SimpleSelectorKind::Class, "a class name")?,
+                Some('[') => self.attribute()?,
+                Some(':') => self.pseudo()?,
+                _ => break,
+            }
+        }
+
+        match self.pos > start {
+            true => Ok(()),
+            false => Err(self.unexpected()),
+        }
+    }
+
+    /// Compound selectors joined by combinators, in a comma separated list
+    fn selector_list(&mut self) -> Result<(), SyntaxError> {
+        let mut expects_compound = true;
+        let mut pending = None;
+
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            let c = match self.peek() {
+                Some(c) => c,
+                None => break,
+            };
+
+            match c {
+                '>' | '+' | '~' | ',' => {
+                    if expects_compound {
+                        return Err(self.unexpected());
+                    }
+                    pending = Some((self.pos, c));
+                    expects_compound = true;
+                    self.bump();
+                }
+                _ if !expects_compound && !had_whitespace => return Err(self.unexpected()),
+                _ => {
+                    self.compound()?;
+                    expects_compound = false;
+                }
+            }
+        }
+
+        match (expects_compound, pending) {
+            (true, Some((pos, c))) => {
+                Err(self.error(pos, pos + 1, format!("Expected a selector after `{c}`")))
+            }
+            (true, None) => Err(self.error(0, self.text.len(), "Expected a CSS selector")),
+            (false, _) => Ok(()),
+        }
+    }
+}
+
+/// Parses a plain CSS selector, as given to `querySelectorAll`
+pub fn parse_css_selector(css: Token<'_>) -> Result<ExtendedSelector<'_>, SyntaxError> {
+    let css = trim(css);
+    let mut selector = ExtendedSelector {
+        prefix: None,
+        css: Some(css),
+        parts: vec![],
+    };
+
+    // Braces are not CSS either, engines like Svelte interpolate with one
+    if is_templated(css.text) || css.text.contains(['{', '}']) {
+        return Ok(selector);
+    }
+
+    let mut parser = CssParser {
+        text: css.text,
+        base: css.start,
+        pos: 0,
+        parts: vec![],
+    };
+    parser.selector_list()?;
+
+    selector.parts = parser.parts;
+    Ok(selector)
+}
+
+/// Parses a selector that may start with one of htmx's keywords, like
+/// `closest form` or `this`. htmx 2 also accepts the selector wrapped as
+/// `<form/>`.
+pub fn parse_extended_selector(value: Token<'_>) -> Result<ExtendedSelector<'_>, SyntaxError> {
+    let value = trim(value);
+    let keyword = value
+        .text
+        .split_whitespace()
+        .next()
+        .filter(|word| EXTENDED_PREFIXES.contains(word))
+        .map(|word| Token {
+            text: word,
+            start: value.start,
+        });
+
+    let css = match keyword {
+        Some(keyword) => trim(Token {
+            text: &value.text[keyword.text.len()..],
+            start: keyword.end(),
+        }),
+        None => value,
+    };
+    let css = match css
+        .text
+        .strip_prefix('<')
+        .and_then(|c| c.strip_suffix("/>"))
+    {
+        Some(inner) => Token {
+            text: inner,
+            start: css.start + 1,
+        },
+        None => css,
+    };
+
+    match keyword {
+        Some(keyword) if STANDALONE_PREFIXES.contains(&keyword.text) && !css.text.is_empty() => {
+            Err(SyntaxError::new(
+                css.range(),
+                format!("`{}` does not take a selector", keyword.text),
+            ))
+        }
+        Some(keyword) if SELECTOR_PREFIXES.contains(&keyword.text) && css.text.is_empty() => {
+            Err(SyntaxError::new(
+                keyword.range(),
+                format!("`{}` expects a CSS selector", keyword.text),
+            ))
+        }
+        Some(keyword) if css.text.is_empty() => Ok(ExtendedSelector {
+            prefix: Some(keyword),
+            css: None,
+            parts: vec![],
+        }),
+        _ => Ok(ExtendedSelector {
+            prefix: keyword,
+            ..parse_css_selector(css)?
+        }),
+    }
+}
+
+impl<'a> Selectors<'a> {
+    fn add(&mut self, selector: Result<ExtendedSelector<'a>, SyntaxError>) {
+        match selector {
+            Ok(selector) => self.selectors.push(selector),
+            Err(err) => self.errors.push(err),
+        }
+    }
+
+    /// A comma separated list where every item may use the extended syntax
+    fn add_list(&mut self, list: Token<'a>) {
+        for item in split_top_level(list, ',') {
+            match trim(item).text.is_empty() {
+                true => self.errors.push(SyntaxError::new(
+                    item.start.saturating_sub(1)..item.start.max(1),
+                    "Empty selector in the list",
+                )),
+                false => self.add(parse_extended_selector(item)),
+            }
+        }
+    }
+
+    fn check_one_of(&mut self, token: Token<'_>, expected: &[&str], what: &str) {
+        if expected.contains(&token.text) {
+            return;
+        }
+
+        let expected = expected
+            .iter()
+            .map(|e| format!("`{e}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.errors.push(SyntaxError::new(
+            token.range(),
+            format!(
+                "Invalid {what} `{}`, expected one of {expected}",
+                token.text
+            ),
+        ));
+    }
+
+    /// `drop`, `abort`, `replace` or `queue`, the latter optionally followed
+    /// by which requests to queue
+    fn check_sync_strategy(&mut self, strategy: Token<'_>) {
+        let words: Vec<_> = tokenize(strategy.text)
+            .into_iter()
+            .map(|word| Token {
+                text: word.text,
+                start: strategy.start + word.start,
+            })
+            .collect();
+
+        match words.as_slice() {
+            [] => self.errors.push(SyntaxError::new(
+                strategy.start.saturating_sub(1)..strategy.start,
+                "Expected a strategy after `:`",
+            )),
+            [queue, option, rest @ ..] if queue.text == "queue" => {
+                self.check_one_of(*option, QUEUE_STRATEGIES, "queue option");
+                self.unexpected_words(rest);
+            }
+            [word, rest @ ..] => {
+                self.check_one_of(*word, SYNC_STRATEGIES, "hx-sync strategy");
+                self.unexpected_words(rest);
+            }
+        }
+    }
+
+    fn unexpected_words(&mut self, words: &[Token<'_>]) {
+        if let Some(word) = words.first() {
+            self.errors.push(SyntaxError::new(
+                word.range(),
+                format!("Unexpected `{}` after the hx-sync strategy", word.text),
+            ));
+        }
+    }
+}
+
+/// Parses the value of an attribute that takes selectors, None for other
+/// attributes
+pub fn parse_selector_attribute<'a>(attribute: &str, value: &'a str) -> Option<Selectors<'a>> {
+    let whole = Token {
+        text: value,
+        start: 0,
+    };
+    let mut selectors = Selectors::default();
+
+    // An empty value falls back to the default, e.g. the element itself
+    if value.trim().is_empty() {
+        return SELECTOR_ATTRIBUTES
+            .contains(&attribute)
+            .then_some(selectors);
+    }
+
+    match attribute {
+        "hx-target" | "hx-include" | "hx-indicator" | "hx-disabled-elt" => {
+            selectors.add_list(whole)
+        }
+        "hx-select" => selectors.add(parse_css_selector(whole)),
+        "hx-select-oob" => {
+            for item in split_top_level(whole, ',') {
+                let (selector, style) = split_first_colon(item);
+                selectors.add(parse_css_selector(selector));
+
+                if let Some(style) = style.map(trim) {
+                    if !swap_styles().any(|known| known == style.text) {
+                        selectors.errors.push(SyntaxError::new(
+                            style.range(),
+                            format!("Unknown hx-swap style `{}`", style.text),
+                        ));
+                    }
+                }
+            }
+        }
+        // The swap style, optionally followed by the element to swap
+        "hx-swap-oob" => {
+            if let (_, Some(selector)) = split_first_colon(whole) {
+                selectors.add(parse_css_selector(selector));
+            }
+        }
+        "hx-sync" => {
+            let (selector, strategy) = split_first_colon(whole);
+            selectors.add(parse_extended_selector(selector));
+
+            if let Some(strategy) = strategy {
+                selectors.check_sync_strategy(strategy);
+            }
+        }
+        _ => return None,
+    }
+
+    Some(selectors)
+}
+
+/// Works out what is valid to type at `offset` of a selector-typed value
+pub fn selector_completion(
+    attribute: &str,
+    value: &str,
+    offset: usize,
+) -> Option<SelectorCompletion> {
+    let before = value.get(..offset)?;
+    if before.trim_end_matches(is_id_char).ends_with('#') {
+        return Some(SelectorCompletion::Id);
+    }
+
+    let item = *split_top_level(
+        Token {
+            text: before,
+            start: 0,
+        },
+        ',',
+    )
+    .last()?;
+
+    let item = match (attribute, split_first_colon(item)) {
+        ("hx-sync" | "hx-select-oob", (_, Some(_))) => return Some(SelectorCompletion::Strategy),
+        ("hx-select" | "hx-select-oob", _) => return None,
+        (_, (selector, _)) => selector,
+    };
+
+    item.text
+        .trim_start()
+        .chars()
+        .all(is_ident_char)
+        .then_some(SelectorCompletion::Prefix)
+}
+
+/// The `#id` of a parsed selector under the cursor
+pub fn selector_id_at<'a>(selectors: &[ExtendedSelector<'a>], offset: usize) -> Option<Token<'a>> {
+    selectors
+        .iter()
+        .flat_map(ExtendedSelector::ids)
+        // Include the `#` itself so the cursor can sit right on it
+        .find(|token| token.start - 1 <= offset && offset <= token.end())
+}
+
+/// Whether an element declared with `declared` as its id can be the target of
+/// `#reference`. Declared ids built by a template only have to agree on their
+/// static prefix.
+pub fn id_matches(declared: &str, reference: &str) -> bool {
+    match declared.find(TEMPLATE_MARKERS) {
+        Some(idx) => reference.starts_with(&declared[..idx]),
+        None => declared == reference,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        id_matches, parse_selector_attribute, selector_completion, SelectorCompletion,
+        SimpleSelectorKind,
+    };
+
+    fn errors(attribute: &str, value: &str) -> Vec<(usize, usize, String)> {
+        parse_selector_attribute(attribute, value)
+            .expect("selector attribute")
+            .errors
+            .into_iter()
+            .map(|err| (err.range.start, err.range.end, err.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_it_parses_css_and_extended_selectors() {
+        let valid = [
+            "#results",
+            "div.card > ul li:nth-child(2n + 1), *[data-id='a,b' i]",
+            "closest tr, find input[name=\"q\"], next, previous .row, this",
+            "document",
+            "closest <form/>",
+            "a\\:b::before ~ p + :not(.done, .skipped)",
+            "#row-{{ item.id }}",
+        ];
+        for value in valid {
+            assert_eq!(errors("hx-target", value), vec![], "{value}");
+        }
+
+        let selectors = parse_selector_attribute("hx-target", "closest form#search .x")
+            .expect("selector attribute")
+            .selectors;
+        let parts: Vec<_> = selectors[0]
+            .parts
+            .iter()
+            .map(|part| (part.kind, part.token.text, part.token.start))
+            .collect();
+
+        assert_eq!(selectors[0].prefix.map(|t| t.text), Some("closest"));
+        assert_eq!(
+            parts,
+            vec![
+                (SimpleSelectorKind::Type, "form", 8),
+                (SimpleSelectorKind::Id, "search", 13),
+                (SimpleSelectorKind::Class, "x", 21),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_it_reports_malformed_selectors_where_they_are() {
+        let error = |value| errors("hx-target", value).into_iter().next();
+        let at = |start, end, message: &str| Some((start, end, message.to_string()));
+
+        assert_eq!(
+            error("#1row"),
+            at(
+                0,
+                5,
+                "`#1row` is not a valid CSS selector, a leading digit has to be escaped"
+            )
+        );
+        assert_eq!(error("div >"), at(4, 5, "Expected a selector after `>`"));
+        assert_eq!(
+            error("input[name"),
+            at(5, 6, "Unclosed `[` in CSS selector")
+        );
+        assert_eq!(error("li:not(.a"), at(6, 7, "Unclosed `(` in CSS selector"));
+        assert_eq!(error("#a, , #b"), at(2, 3, "Empty selector in the list"));
+        assert_eq!(
+            error("closest"),
+            at(0, 7, "`closest` expects a CSS selector")
+        );
+        assert_eq!(
+            error("this .x"),
+            at(5, 7, "`this` does not take a selector")
+        );
+        assert_eq!(error("find a)"), at(6, 7, "Unexpected `)` in CSS selector"));
+        assert_eq!(error("#"), at(0, 1, "Expected an id after `#`"));
+    }
+
+    #[test]
+    fn test_it_reads_sync_strategies_and_oob_swap_styles() {
+        assert_eq!(errors("hx-sync", "closest form:queue last"), vec![]);
+        assert_eq!(
+            errors("hx-sync", "this:queue sometimes"),
+            vec![(
+                11,
+                20,
+                "Invalid queue option `sometimes`, expected one of `first`, `last`, `all`"
+                    .to_string()
+            )]
+        );
+        assert_eq!(errors("hx-select-oob", "#alert:afterbegin, #info"), vec![]);
+        assert_eq!(
+            errors("hx-select-oob", "#alert:after"),
+            vec![(7, 12, "Unknown hx-swap style `after`".to_string())]
+        );
+        assert_eq!(
+            errors("hx-select", "ul >> li"),
+            vec![(4, 5, "Unexpected `>` in CSS selector".to_string())]
+        );
+        assert!(parse_selector_attribute("hx-get", "/search").is_none());
+    }
+
+    #[test]
+    fn test_it_completes_prefixes_ids_and_strategies() {
+        let at_end = |attribute, value: &str| selector_completion(attribute, value, value.len());
+
+        assert_eq!(at_end("hx-target", "clo"), Some(SelectorCompletion::Prefix));
+        assert_eq!(
+            at_end("hx-target", "#a, ne"),
+            Some(SelectorCompletion::Prefix)
+        );
+        assert_eq!(
+            at_end("hx-target", "closest #ro"),
+            Some(SelectorCompletion::Id)
+        );
+        assert_eq!(at_end("hx-target", "closest "), None);
+        assert_eq!(at_end("hx-select", "di"), None);
+        assert_eq!(
+            at_end("hx-sync", "this:"),
+            Some(SelectorCompletion::Strategy)
+        );
+        assert_eq!(
+            at_end("hx-select-oob", "#a:outer"),
+            Some(SelectorCompletion::Strategy)
+        );
+    }
+
+    #[test]
+    fn test_it_reads_the_target_of_out_of_band_swaps() {
+        let ids = |value| {
+            parse_selector_attribute("hx-swap-oob", value)
+                .expect("selector attribute")
+                .selectors
+                .iter()
+                .flat_map(|selector| selector.ids().map(|id| (id.text, id.start)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("outerHTML:#alerts"), vec![("alerts", 11)]);
+        assert_eq!(ids("true"), vec![]);
+    }
+
+    #[test]
+    fn test_id_matches_templated_declarations() {
+        assert!(id_matches("results", "results"));
+        assert!(!id_matches("results", "result"));
+        assert!(id_matches("row-{{ item.id }}", "row-12"));
+        assert!(id_matches("row-<%= id %>", "row-12"));
+        assert!(!id_matches("row-{{ item.id }}", "cell-12"));
+    }
+}
@@ -0,0 +1,345 @@
+// See: https://htmx.org/attributes/hx-swap/
+
+use super::{
+    syntax::{parse_interval, tokenize, SyntaxError, Token},
+    HX_ATTRIBUTE_VALUES,
+};
+
+const SCROLL_POSITIONS: &[&str] = &["top", "bottom"];
+const SHOW_POSITIONS: &[&str] = &["top", "bottom", "none"];
+const BOOLEANS: &[&str] = &["true", "false"];
+const MODIFIERS: &[&str] = &[
+    "swap",
+    "settle",
+    "scroll",
+    "show",
+    "focus-scroll",
+    "transition",
+    "ignoreTitle",
+];
+
+/// Swap styles the built in extensions add. A trailing `:*` stands for any
+/// value, e.g. `multi:#a,#b:outerHTML`.
+pub const EXTENSION_SWAP_STYLES: &[(&str, &[&str])] = &[
+    ("morph", &["morph", "morph:innerHTML", "morph:outerHTML"]),
+    ("alpine-morph", &["morph"]),
+    ("morphdom-swap", &["morphdom"]),
+    ("multi-swap", &["multi:*"]),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SwapModifier<'a> {
+    Swap(Token<'a>),
+    Settle(Token<'a>),
+    Scroll {
+        selector: Option<Token<'a>>,
+        position: Token<'a>,
+    },
+    Show {
+        selector: Option<Token<'a>>,
+        position: Token<'a>,
+    },
+    FocusScroll(Token<'a>),
+    Transition(Token<'a>),
+    IgnoreTitle(Token<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapSpec<'a> {
+    pub style: Option<Token<'a>>,
+    pub modifiers: Vec<SwapModifier<'a>>,
+    pub errors: Vec<SyntaxError>,
+    /// Styles and modifiers that are not known, but an extension the server
+    /// doesn't know about may add
+    pub warnings: Vec<SyntaxError>,
+}
+
+pub fn swap_styles() -> impl Iterator<Item = &'static str> {
+    HX_ATTRIBUTE_VALUES
+        .get("hx-swap")
+        .copied()
+        .unwrap_or_default()
+        .iter()
+        .map(|item| item.name.as_ref())
+}
+
+/// The swap styles of the built in extensions among `extensions`
+pub fn extension_swap_styles(extensions: &[String]) -> Vec<String> {
+    EXTENSION_SWAP_STYLES
+        .iter()
+        .filter(|(extension, _)| extensions.iter().any(|name| name == extension))
+        .flat_map(|(_, styles)| styles.iter().map(|style| style.to_string()))
+        .collect()
+}
+
+fn matches_style(style: &str, text: &str) -> bool {
+    match style.strip_suffix('*') {
+        Some(prefix) => text.len() > prefix.len() && text.starts_with(prefix),
+        None => style == text,
+    }
+}
+
+/// The built in extension that would make `text` a valid swap style
+fn extension_for_style(text: &str) -> Option<&'static str> {
+    EXTENSION_SWAP_STYLES
+        .iter()
+        .find(|(_, styles)| styles.iter().any(|style| matches_style(style, text)))
+        .map(|(extension, _)| *extension)
+}
+
+fn unknown_style(token: Token<'_>) -> SyntaxError {
+    let message = match extension_for_style(token.text) {
+        Some(extension) => format!(
+            "Swap style `{}` needs the `{extension}` extension, enable it with hx-ext",
+            token.text
+        ),
+        None => format!("Unknown hx-swap style `{}`", token.text),
+    };
+
+    SyntaxError::new(token.range(), message)
+}
+
+fn expect_one_of(token: Token<'_>, expected: &[&str], what: &str) -> Result<(), SyntaxError> {
+    if expected.contains(&token.text) {
+        return Ok(());
+    }
+
+    let expected = expected
+        .iter()
+        .map(|e| format!("`{e}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Err(SyntaxError::new(
+        token.range(),
+        format!(
+            "Invalid {what} `{}`, expected one of {expected}",
+            token.text
+        ),
+    ))
+}
+
+/// Splits `[<selector>:]<position>` on the last colon
+fn parse_scroll_target<'a>(
+    value: Token<'a>,
+    positions: &[&str],
+    modifier: &str,
+) -> Result<(Option<Token<'a>>, Token<'a>), SyntaxError> {
+    let (selector, position) = match value.text.rfind(':') {
+        Some(idx) => (
+            Some(Token {
+                text: &value.text[..idx],
+                start: value.start,
+            }),
+            Token {
+                text: &value.text[idx + 1..],
+                start: value.start + idx + 1,
+            },
+        ),
+        None => (None, value),
+    };
+
+    if let Some(selector) = selector {
+        if selector.text.is_empty() {
+            return Err(SyntaxError::new(
+                value.range(),
+                format!(
+                    "Missing selector before `:{}` in `{modifier}:`",
+                    position.text
+                ),
+            ));
+        }
+    }
+
+    expect_one_of(position, positions, &format!("`{modifier}` position"))?;
+
+    Ok((selector, position))
+}
+
+fn parse_modifier<'a>(token: Token<'a>) -> Result<SwapModifier<'a>, SyntaxError> {
+    let (name, value) = match token.text.find(':') {
+        Some(idx) => (
+            &token.text[..idx],
+            Token {
+                text: &token.text[idx + 1..],
+                start: token.start + idx + 1,
+            },
+        ),
+        None => {
+            return Err(SyntaxError::new(
+                token.range(),
+                format!(
+                    "Invalid hx-swap modifier `{}`, modifiers are written as `name:value`",
+                    token.text
+                ),
+            ))
+        }
+    };
+
+    match name {
+        "swap" => parse_interval(value).map(|_| SwapModifier::Swap(value)),
+        "settle" => parse_interval(value).map(|_| SwapModifier::Settle(value)),
+        "scroll" => parse_scroll_target(value, SCROLL_POSITIONS, name)
+            .map(|(selector, position)| SwapModifier::Scroll { selector, position }),
+        "show" => parse_scroll_target(value, SHOW_POSITIONS, name)
+            .map(|(selector, position)| SwapModifier::Show { selector, position }),
+        "focus-scroll" => expect_one_of(value, BOOLEANS, "`focus-scroll` value")
+            .map(|_| SwapModifier::FocusScroll(value)),
+        "transition" => expect_one_of(value, BOOLEANS, "`transition` value")
+            .map(|_| SwapModifier::Transition(value)),
+        "ignoreTitle" => expect_one_of(value, BOOLEANS, "`ignoreTitle` value")
+            .map(|_| SwapModifier::IgnoreTitle(value)),
+        _ => Err(SyntaxError::new(
+            token.start..token.start + name.len(),
+            format!("Unknown hx-swap modifier `{name}`"),
+        )),
+    }
+}
+
+/// Parses `<style> [modifier:value]*`. The style may only be omitted when the
+/// value consists of modifiers alone, in which case htmx uses its default.
+/// `extra_styles` are the styles the enabled extensions add on top of the
+/// built in ones.
+pub fn parse_swap<'a>(value: &'a str, extra_styles: &[String]) -> SwapSpec<'a> {
+    let mut spec = SwapSpec {
+        style: None,
+        modifiers: vec![],
+        errors: vec![],
+        warnings: vec![],
+    };
+
+    let is_extra_style = |text: &str| extra_styles.iter().any(|style| matches_style(style, text));
+    let mut tokens = tokenize(value).into_iter().peekable();
+
+    // Extension styles like `morph:outerHTML` have a colon as well
+    let first = tokens.next_if(|token| {
+        !token.text.contains(':')
+            || is_extra_style(token.text)
+            || extension_for_style(token.text).is_some()
+    });
+    if let Some(first) = first {
+        if !swap_styles().any(|style| style == first.text) && !is_extra_style(first.text) {
+            spec.warnings.push(unknown_style(first));
+        }
+        spec.style = Some(first);
+    }
+
+    for token in tokens {
+        match token.text.split_once(':') {
+            Some((name, _)) if !MODIFIERS.contains(&name) => spec.warnings.push(SyntaxError::new(
+                token.start..token.start + name.len(),
+                format!("Unknown hx-swap modifier `{name}`"),
+            )),
+            _ => match parse_modifier(token) {
+                Ok(modifier) => spec.modifiers.push(modifier),
+                Err(err) => spec.errors.push(err),
+            },
+        }
+    }
+
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extension_swap_styles, parse_swap, SwapModifier};
+    use crate::htmx::syntax::Token;
+
+    #[test]
+    fn test_it_parses_style_and_modifiers() {
+        let spec = parse_swap(
+            "outerHTML swap:1s settle:200ms show:#results:top transition:true",
+            &[],
+        );
+
+        assert!(spec.errors.is_empty(), "{:?}", spec.errors);
+        assert_eq!(
+            spec.style,
+            Some(Token {
+                text: "outerHTML",
+                start: 0
+            })
+        );
+        assert_eq!(spec.modifiers.len(), 4);
+        assert_eq!(
+            spec.modifiers[2],
+            SwapModifier::Show {
+                selector: Some(Token {
+                    text: "#results",
+                    start: 36
+                }),
+                position: Token {
+                    text: "top",
+                    start: 45
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_it_accepts_window_targets_and_flags() {
+        let spec = parse_swap(
+            "beforeend scroll:bottom show:window:top focus-scroll:false ignoreTitle:true",
+            &[],
+        );
+
+        assert!(spec.errors.is_empty(), "{:?}", spec.errors);
+    }
+
+    #[test]
+    fn test_it_reports_unknown_style() {
+        let spec = parse_swap("outerHtml swap:1s", &[]);
+
+        assert!(spec.errors.is_empty(), "{:?}", spec.errors);
+        assert_eq!(spec.warnings.len(), 1);
+        assert_eq!(spec.warnings[0].range, 0..9);
+    }
+
+    #[test]
+    fn test_it_reports_bad_time_units_and_modifiers() {
+        let spec = parse_swap(
+            "innerHTML swap:1sec scroll:middle focus-scroll transiton:true",
+            &[],
+        );
+
+        let ranges: Vec<_> = spec.errors.iter().map(|e| e.range.clone()).collect();
+        assert_eq!(ranges, vec![16..19, 27..33, 34..46]);
+        // An extension may add modifiers
+        let ranges: Vec<_> = spec.warnings.iter().map(|e| e.range.clone()).collect();
+        assert_eq!(ranges, vec![47..56]);
+    }
+
+    #[test]
+    fn test_it_reports_missing_scroll_selector() {
+        let spec = parse_swap("innerHTML show::top", &[]);
+
+        assert_eq!(spec.errors.len(), 1);
+        assert_eq!(spec.errors[0].range, 15..19);
+    }
+
+    #[test]
+    fn test_it_accepts_styles_of_enabled_extensions() {
+        let enabled = extension_swap_styles(&["morph".to_string(), "multi-swap".to_string()]);
+
+        for value in ["morph", "morph:outerHTML swap:1s", "multi:#a,#b:outerHTML"] {
+            let spec = parse_swap(value, &enabled);
+            assert!(spec.errors.is_empty(), "{value}: {:?}", spec.errors);
+            assert!(spec.warnings.is_empty(), "{value}: {:?}", spec.warnings);
+        }
+
+        let custom = parse_swap("morphdom", &["morphdom".to_string()]);
+        assert!(custom.warnings.is_empty(), "{:?}", custom.warnings);
+    }
+
+    #[test]
+    fn test_it_warns_about_styles_of_extensions_that_are_not_enabled() {
+        let spec = parse_swap("morph:innerHTML settle:1s", &[]);
+
+        assert!(spec.errors.is_empty(), "{:?}", spec.errors);
+        assert_eq!(spec.style.map(|style| style.text), Some("morph:innerHTML"));
+        assert_eq!(
+            spec.warnings[0].message,
+            "Swap style `morph:innerHTML` needs the `morph` extension, enable it with hx-ext"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::ops::Range;
+
+/// An error found while parsing an attribute value. The range is a byte range
+/// relative to the start of the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+impl SyntaxError {
+    pub fn new(range: Range<usize>, message: impl Into<String>) -> SyntaxError {
+        SyntaxError {
+            range,
+            message: message.into(),
+        }
+    }
+}
+
+/// A whitespace delimited word of an attribute value along with its byte offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub start: usize,
+}
+
+impl<'a> Token<'a> {
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end()
+    }
+}
+
+pub fn tokenize(value: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut start = None;
+
+    for (idx, c) in value.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                tokens.push(Token {
+                    text: &value[s..idx],
+                    start: s,
+                });
+                start = None;
+            }
+            (false, None) => start = Some(idx),
+            _ => {}
+        }
+    }
+
+    if let Some(s) = start {
+        tokens.push(Token {
+            text: &value[s..],
+            start: s,
+        });
+    }
+
+    tokens
+}
+
+/// Splits a comma separated list such as the `hx-ext` value, dropping the
+/// whitespace around each item
+pub fn split_list(value: &str) -> Vec<Token<'_>> {
+    let mut start = 0;
+
+    value
+        .split(',')
+        .filter_map(|item| {
+            let item_start = start;
+            start += item.len() + 1;
+
+            let trimmed = item.trim_start();
+            let text = trimmed.trim_end();
+            (!text.is_empty()).then_some(Token {
+                text,
+                start: item_start + item.len() - trimmed.len(),
+            })
+        })
+        .collect()
+}
+
+pub const TIME_UNITS: &[&str] = &["ms", "s", "m"];
+
+/// Validates an htmx interval such as `500ms`, `1s`, `2m` or a bare number
+/// of milliseconds
+pub fn parse_interval(token: Token<'_>) -> Result<(), SyntaxError> {
+    let text = token.text;
+    if text.is_empty() {
+        return Err(SyntaxError::new(
+            token.range(),
+            "Expected a time interval such as `500ms` or `1s`",
+        ));
+    }
+
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits);
+
+    if number.is_empty() || number.parse::<f64>().is_err() {
+        return Err(SyntaxError::new(
+            token.range(),
+            format!(
+                "Invalid time interval `{text}`, expected a number followed by `ms`, `s` or `m`"
+            ),
+        ));
+    }
+
+    if !unit.is_empty() && !TIME_UNITS.contains(&unit) {
+        return Err(SyntaxError::new(
+            token.start + digits..token.end(),
+            format!("Invalid time unit `{unit}`, expected `ms`, `s` or `m`"),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_interval, split_list, tokenize, Token};
+
+    #[test]
+    fn test_tokenize_keeps_offsets() {
+        let tokens = tokenize("  innerHTML\tswap:1s ");
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    text: "innerHTML",
+                    start: 2
+                },
+                Token {
+                    text: "swap:1s",
+                    start: 12
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_list_keeps_offsets() {
+        assert_eq!(
+            split_list("json-enc, ignore:sse ,,"),
+            vec![
+                Token {
+                    text: "json-enc",
+                    start: 0
+                },
+                Token {
+                    text: "ignore:sse",
+                    start: 10
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        let token = |text| Token { text, start: 0 };
+
+        assert!(parse_interval(token("500ms")).is_ok());
+        assert!(parse_interval(token("1.5s")).is_ok());
+        assert!(parse_interval(token("200")).is_ok());
+        assert_eq!(parse_interval(token("1sec")).unwrap_err().range, 1..4);
+        assert_eq!(parse_interval(token("fast")).unwrap_err().range, 0..4);
+    }
+}
@@ -0,0 +1,565 @@
+// See: https://htmx.org/attributes/hx-trigger/
+
+use super::{
+    selector::{parse_css_selector, parse_extended_selector, ExtendedSelector},
+    syntax::{parse_interval, SyntaxError, Token},
+};
+
+const QUEUE_OPTIONS: &[&str] = &["first", "last", "all", "none"];
+const RELATIVE_SELECTORS: &[&str] = &["closest", "find", "next", "previous"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerModifier<'a> {
+    pub name: Token<'a>,
+    pub value: Option<Token<'a>>,
+    /// The parsed value of `from:`, `target:` and `root:`
+    pub selector: Option<ExtendedSelector<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerSpec<'a> {
+    pub event: Token<'a>,
+    /// The polling interval of an `every <interval>` trigger
+    pub interval: Option<Token<'a>>,
+    /// The filter expression, without the surrounding brackets
+    pub filter: Option<Token<'a>>,
+    pub modifiers: Vec<TriggerModifier<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triggers<'a> {
+    pub specs: Vec<TriggerSpec<'a>>,
+    pub errors: Vec<SyntaxError>,
+}
+
+/// What can be typed at a given offset of an hx-trigger value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerCompletion {
+    Event,
+    Modifier,
+    Interval,
+    From,
+    Queue,
+}
+
+/// Splits the value on top level commas, keeping empty specs around so they
+/// can be reported
+fn split_specs(value: &str) -> Vec<Token<'_>> {
+    let mut specs = vec![];
+    let mut depth = 0usize;
+    let mut spec_start = 0;
+
+    for (idx, c) in value.char_indices() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                specs.push(Token {
+                    text: &value[spec_start..idx],
+                    start: spec_start,
+                });
+                spec_start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+
+    specs.push(Token {
+        text: &value[spec_start..],
+        start: spec_start,
+    });
+
+    specs
+}
+
+/// Splits a spec on whitespace, ignoring whitespace nested inside `[...]`
+/// filters or `(...)` selectors. Unterminated groups swallow the rest of the
+/// spec, which mirrors how htmx reads them.
+fn words(spec: Token<'_>) -> Vec<Token<'_>> {
+    let mut words = vec![];
+    let mut depth = 0usize;
+    let mut word_start = None;
+
+    for (idx, c) in spec.text.char_indices() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(s) = word_start.take() {
+                    words.push(Token {
+                        text: &spec.text[s..idx],
+                        start: spec.start + s,
+                    });
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        if word_start.is_none() {
+            word_start = Some(idx);
+        }
+    }
+
+    if let Some(s) = word_start {
+        words.push(Token {
+            text: &spec.text[s..],
+            start: spec.start + s,
+        });
+    }
+
+    words
+}
+
+/// Parses `[expression]`, returning the expression
+fn parse_filter(word: Token<'_>) -> Result<Token<'_>, SyntaxError> {
+    match word.text.strip_suffix(']') {
+        Some(inner) if word.text.starts_with('[') => Ok(Token {
+            text: &inner[1..],
+            start: word.start + 1,
+        }),
+        _ => Err(SyntaxError::new(
+            word.range(),
+            "Unclosed `[` in trigger filter",
+        )),
+    }
+}
+
+/// Splits `click[ctrlKey]` into the event and its filter
+fn parse_event(word: Token<'_>) -> Result<(Token<'_>, Option<Token<'_>>), SyntaxError> {
+    match word.text.find('[') {
+        Some(0) => Err(SyntaxError::new(
+            word.range(),
+            "Trigger filter must follow an event name",
+        )),
+        Some(idx) => {
+            let event = Token {
+                text: &word.text[..idx],
+                start: word.start,
+            };
+            let filter = parse_filter(Token {
+                text: &word.text[idx..],
+                start: word.start + idx,
+            })?;
+            Ok((event, Some(filter)))
+        }
+        None => Ok((word, None)),
+    }
+}
+
+fn missing_value(name: Token<'_>, expected: &str) -> SyntaxError {
+    SyntaxError::new(name.range(), format!("`{}:` expects {expected}", name.text))
+}
+
+/// Builds a modifier, reading the value of the ones that take a selector.
+/// Only `from:` accepts htmx's extended selectors.
+fn modifier<'a>(
+    name: Token<'a>,
+    value: Option<Token<'a>>,
+) -> Result<TriggerModifier<'a>, SyntaxError> {
+    let selector = match (name.text, value) {
+        ("from", Some(value)) => Some(parse_extended_selector(value)?),
+        ("target" | "root", Some(value)) => Some(parse_css_selector(value)?),
+        _ => None,
+    };
+
+    Ok(TriggerModifier {
+        name,
+        value,
+        selector,
+    })
+}
+
+fn parse_modifier<'a>(
+    source: &'a str,
+    event: &str,
+    word: Token<'a>,
+    rest: &mut impl Iterator<Item = Token<'a>>,
+) -> Result<TriggerModifier<'a>, SyntaxError> {
+    let (name, value) = match word.text.find(':') {
+        Some(idx) => (
+            Token {
+                text: &word.text[..idx],
+                start: word.start,
+            },
+            Some(Token {
+                text: &word.text[idx + 1..],
+                start: word.start + idx + 1,
+            }),
+        ),
+        None => (word, None),
+    };
+
+    match (name.text, value) {
+        ("once" | "changed" | "consume", None) => {}
+        ("once" | "changed" | "consume", Some(_)) => {
+            return Err(SyntaxError::new(
+                word.range(),
+                format!("`{}` does not take a value", name.text),
+            ))
+        }
+        ("delay" | "throttle", Some(value)) => parse_interval(value)?,
+        ("delay" | "throttle", None) => {
+            return Err(missing_value(name, "a time interval such as `500ms`"))
+        }
+        ("queue", Some(value)) if QUEUE_OPTIONS.contains(&value.text) => {}
+        ("queue", Some(value)) if !value.text.is_empty() => {
+            return Err(SyntaxError::new(
+                value.range(),
+                format!(
+                    "Invalid queue option `{}`, expected one of `first`, `last`, `all`, `none`",
+                    value.text
+                ),
+            ))
+        }
+        ("queue", _) => return Err(missing_value(name, "one of `first`, `last`, `all`, `none`")),
+        ("from", Some(value)) if RELATIVE_SELECTORS.contains(&value.text) => {
+            // htmx reads the next word as the selector, `closest` and `find` require one
+            match rest.next() {
+                Some(selector) => {
+                    return modifier(
+                        name,
+                        Some(Token {
+                            text: &source[value.start..selector.end()],
+                            start: value.start,
+                        }),
+                    )
+                }
+                None if matches!(value.text, "closest" | "find") => {
+                    return Err(SyntaxError::new(
+                        value.range(),
+                        format!("`from:{}` expects a CSS selector", value.text),
+                    ))
+                }
+                None => {}
+            }
+        }
+        ("from" | "target" | "root", Some(value)) if !value.text.is_empty() => {}
+        ("from" | "target" | "root", _) => return Err(missing_value(name, "a CSS selector")),
+        ("threshold", Some(value)) => match value.text.parse::<f64>() {
+            Ok(threshold) if (0.0..=1.0).contains(&threshold) => {}
+            _ => {
+                return Err(SyntaxError::new(
+                    value.range(),
+                    "`threshold:` expects a number between 0.0 and 1.0",
+                ))
+            }
+        },
+        ("threshold", None) => return Err(missing_value(name, "a number between 0.0 and 1.0")),
+        _ => {
+            return Err(SyntaxError::new(
+                name.range(),
+                format!("Unknown hx-trigger modifier `{}`", name.text),
+            ))
+        }
+    }
+
+    if matches!(name.text, "root" | "threshold") && event != "intersect" {
+        return Err(SyntaxError::new(
+            name.range(),
+            format!("`{}:` is only valid on the `intersect` event", name.text),
+        ));
+    }
+
+    modifier(name, value)
+}
+
+fn parse_spec<'a>(
+    source: &'a str,
+    spec: Token<'a>,
+    errors: &mut Vec<SyntaxError>,
+) -> Option<TriggerSpec<'a>> {
+    let mut words = words(spec).into_iter().peekable();
+
+    let first = match words.next() {
+        Some(first) => first,
+        None => {
+            errors.push(SyntaxError::new(
+                spec.range(),
+                "Empty trigger specification",
+            ));
+            return None;
+        }
+    };
+
+    let (event, mut filter) = match parse_event(first) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            errors.push(err);
+            return None;
+        }
+    };
+
+    let mut interval = None;
+    if event.text == "every" {
+        match words.next() {
+            Some(word) => match parse_interval(word) {
+                Ok(()) => interval = Some(word),
+                Err(err) => errors.push(err),
+            },
+            None => errors.push(SyntaxError::new(
+                event.range(),
+                "`every` expects a time interval such as `1s`",
+            )),
+        }
+    }
+
+    if let Some(word) = words.next_if(|word| word.text.starts_with('[')) {
+        if filter.is_some() || interval.is_none() {
+            errors.push(SyntaxError::new(
+                word.range(),
+                "Trigger filter must directly follow the event name",
+            ));
+        } else {
+            match parse_filter(word) {
+                Ok(parsed) => filter = Some(parsed),
+                Err(err) => errors.push(err),
+            }
+        }
+    }
+
+    let mut modifiers = vec![];
+    while let Some(word) = words.next() {
+        match parse_modifier(source, event.text, word, &mut words) {
+            Ok(modifier) => modifiers.push(modifier),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    Some(TriggerSpec {
+        event,
+        interval,
+        filter,
+        modifiers,
+    })
+}
+
+pub fn parse_trigger(value: &str) -> Triggers<'_> {
+    let mut triggers = Triggers {
+        specs: vec![],
+        errors: vec![],
+    };
+
+    // An empty value falls back to the element's default trigger
+    if value.trim().is_empty() {
+        return triggers;
+    }
+
+    for spec in split_specs(value) {
+        if let Some(spec) = parse_spec(value, spec, &mut triggers.errors) {
+            triggers.specs.push(spec);
+        }
+    }
+
+    triggers
+}
+
+/// Works out what is valid to type at `offset`, or None when the cursor sits
+/// somewhere free form such as a filter expression or a CSS selector
+pub fn trigger_completion(value: &str, offset: usize) -> Option<TriggerCompletion> {
+    let prefix = value.get(..offset)?;
+    let spec = *split_specs(prefix).last()?;
+
+    let unclosed = spec.text.chars().fold(0i32, |depth, c| match c {
+        '[' | '(' => depth + 1,
+        ']' | ')' => depth - 1,
+        _ => depth,
+    });
+    if unclosed > 0 {
+        return None;
+    }
+
+    let mut words = words(spec);
+    let current = match spec.text.ends_with(char::is_whitespace) {
+        true => "",
+        false => words.pop().map(|word| word.text).unwrap_or_default(),
+    };
+
+    match words.last().map(|word| word.text) {
+        None => return Some(TriggerCompletion::Event),
+        Some("every") if words.len() == 1 => return Some(TriggerCompletion::Interval),
+        Some(previous) => {
+            if let Some(relative) = previous.strip_prefix("from:") {
+                if RELATIVE_SELECTORS.contains(&relative) {
+                    return None;
+                }
+            }
+        }
+    }
+
+    match current.split_once(':') {
+        Some(("delay" | "throttle", _)) => Some(TriggerCompletion::Interval),
+        Some(("queue", _)) => Some(TriggerCompletion::Queue),
+        Some(("from", _)) => Some(TriggerCompletion::From),
+        Some(_) => None,
+        None => Some(TriggerCompletion::Modifier),
+    }
+}
+
+/// The word of the value under `offset`, classified the way completion would
+/// classify it. Modifiers are returned with their colon, e.g. `delay:`, and
+/// the value of a modifier is classified by the modifier.
+pub fn trigger_word_at(value: &str, offset: usize) -> Option<(TriggerCompletion, Token<'_>)> {
+    let contains = |token: &Token<'_>| (token.start..=token.end()).contains(&offset);
+
+    let spec = split_specs(value).into_iter().find(contains)?;
+    let word = words(spec).into_iter().find(contains)?;
+
+    // Filters are free form expressions
+    let word = match word.text.find('[') {
+        Some(0) => return None,
+        Some(idx) if offset > word.start + idx => return None,
+        Some(idx) => Token {
+            text: &word.text[..idx],
+            start: word.start,
+        },
+        None => word,
+    };
+
+    match word.text.split_once(':') {
+        Some((name, rest)) if offset > word.start + name.len() => {
+            let modifier_value = Token {
+                text: rest,
+                start: word.start + name.len() + 1,
+            };
+            Some((
+                trigger_completion(value, modifier_value.start)?,
+                modifier_value,
+            ))
+        }
+        Some((name, _)) => Some((
+            TriggerCompletion::Modifier,
+            Token {
+                text: &word.text[..=name.len()],
+                start: word.start,
+            },
+        )),
+        None => Some((trigger_completion(value, word.start)?, word)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_trigger, trigger_completion, trigger_word_at, TriggerCompletion};
+
+    #[test]
+    fn test_it_parses_multiple_specs_with_filters_and_modifiers() {
+        let triggers =
+            parse_trigger("keyup[key=='Enter'] changed delay:500ms from:closest form, every 2s [isActive()], load");
+
+        assert!(triggers.errors.is_empty(), "{:?}", triggers.errors);
+        assert_eq!(triggers.specs.len(), 3);
+
+        let keyup = &triggers.specs[0];
+        assert_eq!(keyup.event.text, "keyup");
+        assert_eq!(keyup.filter.map(|f| f.text), Some("key=='Enter'"));
+        let modifiers: Vec<_> = keyup
+            .modifiers
+            .iter()
+            .map(|m| (m.name.text, m.value.map(|v| v.text)))
+            .collect();
+        assert_eq!(
+            modifiers,
+            vec![
+                ("changed", None),
+                ("delay", Some("500ms")),
+                ("from", Some("closest form"))
+            ]
+        );
+
+        let every = &triggers.specs[1];
+        assert_eq!(every.interval.map(|i| i.text), Some("2s"));
+        assert_eq!(every.filter.map(|f| f.text), Some("isActive()"));
+    }
+
+    #[test]
+    fn test_it_accepts_intersect_options_and_queue() {
+        let triggers =
+            parse_trigger("intersect once root:#list threshold:0.5, click queue:none consume");
+
+        assert!(triggers.errors.is_empty(), "{:?}", triggers.errors);
+    }
+
+    #[test]
+    fn test_it_reports_malformed_specs() {
+        let value = "click delay:5 sec, , every, keyup queue:sometimes threshold:0.2 from:";
+        let triggers = parse_trigger(value);
+
+        let errors: Vec<_> = triggers
+            .errors
+            .iter()
+            .map(|e| &value[e.range.clone()])
+            .collect();
+        assert_eq!(
+            errors,
+            vec!["sec", " ", "every", "sometimes", "threshold", "from"]
+        );
+    }
+
+    #[test]
+    fn test_it_reads_modifier_selectors() {
+        let value = "click from:document, keyup from:closest #1form, load target:.a:";
+        let triggers = parse_trigger(value);
+
+        let errors: Vec<_> = triggers
+            .errors
+            .iter()
+            .map(|e| &value[e.range.clone()])
+            .collect();
+        assert_eq!(errors, vec!["#1form", ":"]);
+
+        let selector = triggers.specs[0].modifiers[0].selector.as_ref();
+        assert_eq!(
+            selector.and_then(|s| s.prefix).map(|t| t.text),
+            Some("document")
+        );
+    }
+
+    #[test]
+    fn test_it_reports_unclosed_filters() {
+        let triggers = parse_trigger("click[ctrlKey once");
+
+        assert_eq!(triggers.errors.len(), 1);
+        assert_eq!(triggers.errors[0].range, 5..18);
+    }
+
+    #[test]
+    fn test_it_completes_based_on_cursor_context() {
+        let at_end = |value: &str| trigger_completion(value, value.len());
+
+        assert_eq!(at_end(""), Some(TriggerCompletion::Event));
+        assert_eq!(at_end("cli"), Some(TriggerCompletion::Event));
+        assert_eq!(at_end("click "), Some(TriggerCompletion::Modifier));
+        assert_eq!(at_end("click on"), Some(TriggerCompletion::Modifier));
+        assert_eq!(at_end("click delay:"), Some(TriggerCompletion::Interval));
+        assert_eq!(
+            at_end("click throttle:5"),
+            Some(TriggerCompletion::Interval)
+        );
+        assert_eq!(at_end("every "), Some(TriggerCompletion::Interval));
+        assert_eq!(at_end("click from:"), Some(TriggerCompletion::From));
+        assert_eq!(at_end("click queue:"), Some(TriggerCompletion::Queue));
+        assert_eq!(at_end("click, "), Some(TriggerCompletion::Event));
+        assert_eq!(at_end("click[ctrlKey "), None);
+        assert_eq!(at_end("click from:closest "), None);
+        assert_eq!(at_end("click target:"), None);
+    }
+
+    #[test]
+    fn test_it_classifies_the_word_under_the_cursor() {
+        let value =
+            "keyup[key=='Enter'] changed delay:500ms, every 2s, click queue:last from:closest form";
+        let word_at = |offset| trigger_word_at(value, offset).map(|(kind, word)| (kind, word.text));
+
+        assert_eq!(word_at(2), Some((TriggerCompletion::Event, "keyup")));
+        assert_eq!(word_at(10), None);
+        assert_eq!(word_at(22), Some((TriggerCompletion::Modifier, "changed")));
+        assert_eq!(word_at(30), Some((TriggerCompletion::Modifier, "delay:")));
+        assert_eq!(word_at(36), Some((TriggerCompletion::Interval, "500ms")));
+        assert_eq!(word_at(42), Some((TriggerCompletion::Event, "every")));
+        assert_eq!(word_at(48), Some((TriggerCompletion::Interval, "2s")));
+        assert_eq!(word_at(65), Some((TriggerCompletion::Queue, "last")));
+        assert_eq!(word_at(75), Some((TriggerCompletion::From, "closest")));
+        assert_eq!(word_at(84), None);
+    }
+}
@@ -0,0 +1,303 @@
+// See: https://htmx.org/migration-guide-htmx-1/
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, OnceLock},
+};
+
+use crate::{
+    config::{config, HtmxVersion},
+    text_store::Document,
+    tree_sitter_querier::query_script_sources,
+};
+
+use super::HxDocItem;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Added(HtmxVersion),
+    Deprecated(HtmxVersion),
+    Removed(HtmxVersion),
+}
+
+/// How an attribute, or one of its values, changed between htmx versions
+#[derive(Debug)]
+pub struct CatalogChange {
+    pub attribute: &'static str,
+    pub value: Option<&'static str>,
+    pub change: Change,
+    pub note: &'static str,
+}
+
+const fn change(
+    attribute: &'static str,
+    value: Option<&'static str>,
+    change: Change,
+    note: &'static str,
+) -> CatalogChange {
+    CatalogChange {
+        attribute,
+        value,
+        change,
+        note,
+    }
+}
+
+pub static CATALOG_CHANGES: &[CatalogChange] = &[
+    change(
+        "hx-sse",
+        None,
+        Change::Deprecated(HtmxVersion::V1),
+        "use the `sse` extension with `sse-connect` and `sse-swap` instead",
+    ),
+    change(
+        "hx-sse",
+        None,
+        Change::Removed(HtmxVersion::V2),
+        "use the `sse` extension with `sse-connect` and `sse-swap` instead",
+    ),
+    change(
+        "hx-ws",
+        None,
+        Change::Deprecated(HtmxVersion::V1),
+        "use the `ws` extension with `ws-connect` and `ws-send` instead",
+    ),
+    change(
+        "hx-ws",
+        None,
+        Change::Removed(HtmxVersion::V2),
+        "use the `ws` extension with `ws-connect` and `ws-send` instead",
+    ),
+    change(
+        "hx-vars",
+        None,
+        Change::Deprecated(HtmxVersion::V1),
+        "use `hx-vals` with `js:` values instead",
+    ),
+    change(
+        "hx-on",
+        None,
+        Change::Deprecated(HtmxVersion::V1),
+        "use one `hx-on:<event>` attribute per event instead",
+    ),
+    change(
+        "hx-on",
+        None,
+        Change::Removed(HtmxVersion::V2),
+        "use one `hx-on:<event>` attribute per event instead",
+    ),
+    change("hx-inherit", None, Change::Added(HtmxVersion::V2), ""),
+    change(
+        "hx-swap",
+        Some("textContent"),
+        Change::Added(HtmxVersion::V2),
+        "",
+    ),
+    change(
+        "hx-ext",
+        Some("disable-element"),
+        Change::Deprecated(HtmxVersion::V1),
+        "use `hx-disabled-elt` instead",
+    ),
+    change(
+        "hx-ext",
+        Some("disable-element"),
+        Change::Removed(HtmxVersion::V2),
+        "use `hx-disabled-elt` instead",
+    ),
+];
+
+/// Whether an item can be used with a given htmx version
+#[derive(Debug, Clone, PartialEq)]
+pub enum Availability {
+    Available,
+    Deprecated(String),
+    Removed(String),
+    NotYetAdded(String),
+}
+
+/// Looks up an attribute, or one of its values, in the catalog changes. An
+/// unknown version only reports deprecations, as everything else depends on
+/// which version the project ends up using.
+pub fn availability(
+    attribute: &str,
+    value: Option<&str>,
+    version: Option<HtmxVersion>,
+) -> Availability {
+    let subject = match value {
+        Some(value) => format!("`{value}`"),
+        None => format!("`{attribute}`"),
+    };
+
+    let mut availability = Availability::Available;
+    for item in CATALOG_CHANGES
+        .iter()
+        .filter(|item| item.attribute == attribute && item.value == value)
+    {
+        match (item.change, version) {
+            (Change::Added(since), Some(version)) if version < since => {
+                return Availability::NotYetAdded(format!(
+                    "{subject} requires {since}, but the project uses {version}"
+                ));
+            }
+            (Change::Removed(since), Some(version)) if version >= since => {
+                return Availability::Removed(format!(
+                    "{subject} was removed in {since}, {}",
+                    item.note
+                ));
+            }
+            (Change::Deprecated(since), version)
+                if version.map(|version| version >= since).unwrap_or(true) =>
+            {
+                availability =
+                    Availability::Deprecated(format!("{subject} is deprecated, {}", item.note));
+            }
+            _ => {}
+        }
+    }
+
+    availability
+}
+
+/// The catalog items that exist in `version`
+pub fn available_items(
+    attribute: Option<&str>,
+    items: &[HxDocItem],
+    version: Option<HtmxVersion>,
+) -> Vec<HxDocItem> {
+    items
+        .iter()
+        .filter(|item| {
+            let status = match attribute {
+                Some(attribute) => availability(attribute, Some(&item.name), version),
+                None => availability(&item.name, None, version),
+            };
+            !matches!(
+                status,
+                Availability::Removed(_) | Availability::NotYetAdded(_)
+            )
+        })
+        .cloned()
+        .collect()
+}
+
+/// Finds the major version in a script url such as
+/// `https://unpkg.com/htmx.org@2.0.1/dist/htmx.min.js`
+pub fn detect_version(src: &str) -> Option<HtmxVersion> {
+    ["htmx.org@", "htmx@"].iter().find_map(|package| {
+        let idx = src.find(package)?;
+        src[idx + package.len()..]
+            .chars()
+            .next()
+            .and_then(HtmxVersion::from_major)
+    })
+}
+
+/// The htmx version the script tags of each document load, keyed by uri
+fn detected_versions() -> MutexGuard<'static, HashMap<String, HtmxVersion>> {
+    static DETECTED: OnceLock<Mutex<HashMap<String, HtmxVersion>>> = OnceLock::new();
+
+    DETECTED
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .expect("detected versions mutex poisoned")
+}
+
+pub fn detect_document_version(uri: &str, document: &Document) {
+    let version = query_script_sources(document.tree.root_node(), &document.text)
+        .iter()
+        .find_map(|src| detect_version(&src.value));
+
+    match version {
+        Some(version) => detected_versions().insert(uri.to_string(), version),
+        None => detected_versions().remove(uri),
+    };
+}
+
+pub fn forget_document_version(uri: &str) {
+    detected_versions().remove(uri);
+}
+
+/// The version the document at `uri` loads with its own script tag, or else
+/// the one set in the configuration, or else the newest one loaded anywhere
+/// in the workspace
+pub fn htmx_version(uri: Option<&str>) -> Option<HtmxVersion> {
+    uri.and_then(|uri| detected_versions().get(uri).copied())
+        .or_else(|| config().htmx_version)
+        .or_else(|| detected_versions().values().max().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        availability, detect_document_version, detect_version, htmx_version, Availability,
+    };
+    use crate::{config::HtmxVersion, text_store::Document};
+
+    #[test]
+    fn test_detect_version() {
+        assert_eq!(
+            detect_version("https://unpkg.com/htmx.org@2.0.1/dist/htmx.min.js"),
+            Some(HtmxVersion::V2)
+        );
+        assert_eq!(
+            detect_version("https://cdn.jsdelivr.net/npm/htmx.org@1.9.12"),
+            Some(HtmxVersion::V1)
+        );
+        assert_eq!(detect_version("/static/htmx.min.js"), None);
+    }
+
+    #[test]
+    fn test_htmx_version_prefers_the_document_own_version() {
+        let script = |version| {
+            Document::new(format!(
+                r#"<script src="https://unpkg.com/htmx.org@{version}"></script>"#
+            ))
+        };
+        detect_document_version("file:///legacy-page.html", &script("1.9.12"));
+        detect_document_version("file:///modern-page.html", &script("2.0.4"));
+
+        assert_eq!(
+            htmx_version(Some("file:///legacy-page.html")),
+            Some(HtmxVersion::V1)
+        );
+        assert_eq!(
+            htmx_version(Some("file:///modern-page.html")),
+            Some(HtmxVersion::V2)
+        );
+        // Documents without a script tag go by the rest of the workspace
+        assert_eq!(
+            htmx_version(Some("file:///partial.html")),
+            Some(HtmxVersion::V2)
+        );
+    }
+
+    #[test]
+    fn test_availability_follows_the_version() {
+        assert_eq!(
+            availability("hx-get", None, Some(HtmxVersion::V2)),
+            Availability::Available
+        );
+        assert!(matches!(
+            availability("hx-sse", None, Some(HtmxVersion::V1)),
+            Availability::Deprecated(_)
+        ));
+        assert!(matches!(
+            availability("hx-sse", None, Some(HtmxVersion::V2)),
+            Availability::Removed(_)
+        ));
+        assert!(matches!(
+            availability("hx-inherit", None, Some(HtmxVersion::V1)),
+            Availability::NotYetAdded(_)
+        ));
+        // Without a known version removals are not reported
+        assert!(matches!(
+            availability("hx-on", None, None),
+            Availability::Deprecated(_)
+        ));
+        assert!(matches!(
+            availability("hx-swap", Some("textContent"), Some(HtmxVersion::V1)),
+            Availability::NotYetAdded(_)
+        ));
+    }
+}
@@ -1,127 +1,364 @@
+mod cancel;
+mod code_actions;
+mod config;
+mod custom_data;
+mod diagnostics;
 mod handle;
 mod htmx;
+mod scheduler;
 mod text_store;
 mod tree_sitter;
 mod tree_sitter_querier;
+mod workspace;
+
+use std::panic::AssertUnwindSafe;
 
 use anyhow::Result;
-use htmx::HxDocItem;
+use crossbeam_channel::Sender;
 use log::{debug, error, info, warn};
 use lsp_types::{
-    ClientInfo, CompletionItem, CompletionItemKind, CompletionList, HoverContents,
-    InitializeParams, MarkupContent, ServerCapabilities, TextDocumentSyncCapability,
-    TextDocumentSyncKind, WorkDoneProgressOptions,
+    notification::{Notification as _, PublishDiagnostics},
+    ClientInfo, CodeActionKind, CodeActionOptions, CodeActionProviderCapability, CompletionItem,
+    CompletionItemKind, CompletionList, CompletionTextEdit, Documentation, GotoDefinitionResponse,
+    HoverContents, InitializeParams, InsertTextFormat, MarkupContent, MarkupKind,
+    PublishDiagnosticsParams, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
+    TextDocumentSyncOptions, TextDocumentSyncSaveOptions, TextEdit, Url, WorkDoneProgressOptions,
 };
 
-use lsp_server::{Connection, Message, Response};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
 
 use crate::{
-    handle::{handle_notification, handle_other, handle_request, HtmxResult},
-    text_store::init_text_store,
+    cancel::{
+        cancelled_response, finish_request, init_in_flight_requests, is_cancelled, track_message,
+    },
+    config::{configuration_request, init_config},
+    custom_data::watch_request,
+    handle::{
+        handle_notification, handle_other, handle_request, handle_scans_finished,
+        HtmxAttributeCompletion, HtmxDiagnostics, HtmxResult,
+    },
+    htmx::{attribute_snippet, init_snippet_support, version::htmx_version},
+    scheduler::WorkerPool,
+    text_store::{get_document, init_position_encoding, init_text_store, with_snapshot, Document},
+    workspace::{index_workspace, init_workspace_index, on_scans_finished},
 };
 
-fn to_completion_list(items: Vec<HxDocItem>) -> CompletionList {
+fn to_completion_list(completion: HtmxAttributeCompletion) -> CompletionList {
+    // Documentation is filled in by completionItem/resolve, the data tells
+    // it where to look the item up
+    let data = serde_json::json!({
+        "attribute": completion.attribute,
+        "uri": completion.uri,
+    });
+    let version = htmx_version(Some(completion.uri.as_str()));
+
     CompletionList {
         is_incomplete: true,
-        items: items
+        items: completion
+            .items
             .iter()
-            .map(|x| CompletionItem {
-                label: x.name.to_string(),
-                label_details: None,
-                kind: Some(CompletionItemKind::PROPERTY),
-                detail: Some(x.desc.to_string()),
-                documentation: None,
-                deprecated: Some(false),
-                preselect: None,
-                sort_text: None,
-                filter_text: None,
-                insert_text: None,
-                insert_text_format: None,
-                insert_text_mode: None,
-                text_edit: None,
-                additional_text_edits: None,
-                command: None,
-                commit_characters: None,
-                data: None,
-                tags: None,
+            .enumerate()
+            .map(|(idx, x)| {
+                let (new_text, insert_text_format) = match completion.snippets {
+                    true => (
+                        attribute_snippet(&x.name, version),
+                        InsertTextFormat::SNIPPET,
+                    ),
+                    false => (x.name.to_string(), InsertTextFormat::PLAIN_TEXT),
+                };
+
+                CompletionItem {
+                    label: x.name.to_string(),
+                    label_details: None,
+                    kind: Some(CompletionItemKind::PROPERTY),
+                    detail: None,
+                    documentation: None,
+                    deprecated: Some(false),
+                    preselect: None,
+                    // Keeps the ranking of the server over the client's sorting
+                    sort_text: Some(format!("{idx:04}")),
+                    filter_text: Some(x.name.to_string()),
+                    insert_text: None,
+                    insert_text_format: Some(insert_text_format),
+                    insert_text_mode: None,
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                        range: completion.range,
+                        new_text,
+                    })),
+                    additional_text_edits: None,
+                    command: None,
+                    commit_characters: None,
+                    data: Some(data.clone()),
+                    tags: None,
+                }
             })
+            .chain(completion.patterns.iter().map(|pattern| CompletionItem {
+                label: pattern.label.clone(),
+                kind: Some(CompletionItemKind::SNIPPET),
+                detail: Some(pattern.description.clone()),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```html\n{}\n```", pattern.body),
+                })),
+                filter_text: Some(pattern.filter_text.clone()),
+                insert_text_format: Some(InsertTextFormat::SNIPPET),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range: completion.range,
+                    new_text: pattern.body.clone(),
+                })),
+                ..Default::default()
+            }))
             .collect(),
     }
 }
 
-fn main_loop(connection: Connection, params: serde_json::Value) -> Result<()> {
-    let params: InitializeParams = serde_json::from_value(params).unwrap();
+fn diagnostics_notification(d: HtmxDiagnostics) -> Message {
+    let params = PublishDiagnosticsParams {
+        uri: d.uri,
+        diagnostics: d.diagnostics,
+        version: None,
+    };
 
-    info!("STARTING EXAMPLE MAIN LOOP");
+    Message::Notification(Notification::new(
+        PublishDiagnostics::METHOD.to_string(),
+        params,
+    ))
+}
 
-    for msg in &connection.receiver {
-        error!("connection received message: {:?}", msg);
-        let id = match &msg {
-            Message::Request(ref req) => Some(req.id.clone()),
-            _ => None,
-        };
-        let result = match msg {
-            Message::Notification(not) => handle_notification(not),
-            Message::Request(req) => handle_request(req),
-            _ => handle_other(msg),
-        };
+/// Turns the outcome of a handler into the messages sent to the client
+fn to_messages(
+    result: Option<HtmxResult>,
+    id: Option<RequestId>,
+    skip_null_responses: bool,
+) -> Vec<Message> {
+    match result {
+        Some(HtmxResult::WorkspaceDiagnostics(diagnostics)) => diagnostics
+            .into_iter()
+            .map(diagnostics_notification)
+            .collect(),
+        result => to_message(result, id, skip_null_responses)
+            .into_iter()
+            .collect(),
+    }
+}
 
-        match match result {
-            Some(HtmxResult::AttributeCompletion(c)) => {
-                let str = match serde_json::to_value(to_completion_list(c.items)) {
-                    Ok(s) => s,
-                    Err(_) => continue,
-                };
+fn to_message(
+    result: Option<HtmxResult>,
+    id: Option<RequestId>,
+    skip_null_responses: bool,
+) -> Option<Message> {
+    match result {
+        Some(HtmxResult::Diagnostics(d)) => Some(diagnostics_notification(d)),
+
+        Some(HtmxResult::WorkspaceDiagnostics(_)) => None,
+
+        Some(HtmxResult::ConfigurationRequest(req)) => Some(Message::Request(req)),
+
+        Some(HtmxResult::AttributeCompletion(c)) => {
+            let id = c.id.clone();
+            let str = serde_json::to_value(to_completion_list(c)).ok()?;
+
+            Some(Message::Response(Response {
+                id,
+                result: Some(str),
+                error: None,
+            }))
+        }
 
-                // TODO: block requests that have been cancelled
-                connection.sender.send(Message::Response(Response {
-                    id: c.id,
-                    result: Some(str),
-                    error: None,
-                }))
+        Some(HtmxResult::CompletionResolve(resolve)) => {
+            let str = serde_json::to_value(resolve.item).ok()?;
+
+            Some(Message::Response(Response {
+                id: resolve.id,
+                result: Some(str),
+                error: None,
+            }))
+        }
+
+        Some(HtmxResult::AttributeHover(hover_resp)) => {
+            debug!("main_loop - hover response: {:?}", hover_resp);
+            let hover_response = lsp_types::Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: lsp_types::MarkupKind::Markdown,
+                    value: hover_resp.value.to_string(),
+                }),
+                range: None,
+            };
+
+            let str = match serde_json::to_value(&hover_response) {
+                Ok(s) => s,
+                Err(err) => {
+                    error!("Fail to parse hover_response: {:?}", err);
+                    return None;
+                }
+            };
+
+            Some(Message::Response(Response {
+                id: hover_resp.id,
+                result: Some(str),
+                error: None,
+            }))
+        }
+        Some(HtmxResult::Definition(definition)) => {
+            let str =
+                serde_json::to_value(GotoDefinitionResponse::Array(definition.locations)).ok()?;
+
+            Some(Message::Response(Response {
+                id: definition.id,
+                result: Some(str),
+                error: None,
+            }))
+        }
+        Some(HtmxResult::CodeActions(code_actions)) => {
+            let str = serde_json::to_value(code_actions.actions).ok()?;
+
+            Some(Message::Response(Response {
+                id: code_actions.id,
+                result: Some(str),
+                error: None,
+            }))
+        }
+        None => {
+            // Sending a response with `result == None` will crash the helix client
+            if skip_null_responses {
+                return None;
             }
 
-            Some(HtmxResult::AttributeHover(hover_resp)) => {
-                debug!("main_loop - hover response: {:?}", hover_resp);
-                let hover_response = lsp_types::Hover {
-                    contents: HoverContents::Markup(MarkupContent {
-                        kind: lsp_types::MarkupKind::Markdown,
-                        value: hover_resp.value.to_string(),
-                    }),
-                    range: None,
-                };
+            Some(Message::Response(Response {
+                id: id?,
+                result: None,
+                error: None,
+            }))
+        }
+    }
+}
 
-                let str = match serde_json::to_value(&hover_response) {
-                    Ok(s) => s,
-                    Err(err) => {
-                        error!("Fail to parse hover_response: {:?}", err);
-                        return Err(anyhow::anyhow!("Fail to parse hover_response"));
-                    }
-                };
+/// The document a request refers to, as it is when the request is received
+fn request_snapshot(req: &Request) -> Option<(String, Document)> {
+    let uri = req.params.get("textDocument")?.get("uri")?.as_str()?;
+    let document = get_document(&Url::parse(uri).ok()?)?;
 
-                connection.sender.send(Message::Response(Response {
-                    id: hover_resp.id,
-                    result: Some(str),
-                    error: None,
-                }))
+    Some((uri.to_string(), document))
+}
+
+/// Runs a request on the worker pool against a snapshot of its document.
+/// Requests that are cancelled or superseded before or while they run are
+/// answered with an error instead of their result.
+fn dispatch_request(
+    pool: &WorkerPool,
+    sender: &Sender<Message>,
+    req: Request,
+    skip_null_responses: bool,
+) {
+    run_request(pool, sender, req, skip_null_responses, handle_request);
+}
+
+/// Answers `req` with what `handle` returns, or with an internal error when it
+/// panics, so the client is never left waiting for a response
+fn run_request(
+    pool: &WorkerPool,
+    sender: &Sender<Message>,
+    req: Request,
+    skip_null_responses: bool,
+    handle: fn(Request) -> Option<HtmxResult>,
+) {
+    let sender = sender.clone();
+    let snapshot = request_snapshot(&req);
+
+    pool.execute(move || {
+        let id = req.id.clone();
+
+        let result = match is_cancelled(&id) {
+            true => {
+                debug!("main_loop - skipping cancelled request {}", id);
+                Ok(None)
             }
-            None => {
-                // Sending a response with `result == None` will crash the helix client
-                let id = match (id, &params.client_info) {
-                    (_, Some(ClientInfo { name, .. })) if name.eq("helix") => continue,
-                    (Some(id), _) => id,
-                    _ => continue,
-                };
-                connection.sender.send(Message::Response(Response {
+            false => std::panic::catch_unwind(AssertUnwindSafe(|| match snapshot {
+                Some((uri, document)) => with_snapshot(&uri, document, || handle(req)),
+                None => handle(req),
+            })),
+        };
+
+        let message = match (finish_request(&id), result) {
+            (Some(cancellation), _) => {
+                Some(Message::Response(cancelled_response(id, cancellation)))
+            }
+            (None, Err(_)) => {
+                error!("request {} panicked", id);
+                Some(Message::Response(Response::new_err(
                     id,
-                    result: None,
-                    error: None,
-                }))
+                    ErrorCode::InternalError as i32,
+                    "request handler panicked".to_string(),
+                )))
+            }
+            (None, Ok(result)) => to_message(result, Some(id), skip_null_responses),
+        };
+
+        if let Some(message) = message {
+            if let Err(e) = sender.send(message) {
+                error!("failed to send response: {:?}", e);
             }
-        } {
-            Ok(_) => {}
-            Err(e) => error!("failed to send response: {:?}", e),
+        }
+    });
+}
+
+fn main_loop(connection: Connection, params: serde_json::Value) -> Result<()> {
+    let params: InitializeParams = serde_json::from_value(params).unwrap();
+    let skip_null_responses = matches!(
+        &params.client_info,
+        Some(ClientInfo { name, .. }) if name.eq("helix")
+    );
+
+    // Unknown ids are not reported while the workspace is being scanned
+    let sender = connection.sender.clone();
+    on_scans_finished(move || {
+        for msg in to_messages(handle_scans_finished(), None, skip_null_responses) {
+            if let Err(err) = sender.send(msg) {
+                error!("failed to publish diagnostics after indexing: {:?}", err);
+            }
+        }
+    });
+    index_workspace(&params);
+
+    // The handshake is over by now, so the client is ready to be asked for
+    // settings it did not pass in initializationOptions
+    for req in [configuration_request(), watch_request(&params)]
+        .into_iter()
+        .flatten()
+    {
+        connection.sender.send(Message::Request(req))?;
+    }
+
+    info!("STARTING EXAMPLE MAIN LOOP");
+
+    // Requests run in parallel while notifications are applied here, in the
+    // order they were sent, so every request sees the edits made before it
+    let pool = WorkerPool::with_available_parallelism();
+
+    for msg in &connection.receiver {
+        error!("connection received message: {:?}", msg);
+        track_message(&msg);
+
+        let messages = match msg {
+            Message::Request(req) => {
+                // Answers shutdown and waits for the exit notification that follows
+                if connection.handle_shutdown(&req)? {
+                    info!("received shutdown request, stopping main loop");
+                    return Ok(());
+                }
+
+                dispatch_request(&pool, &connection.sender, req, skip_null_responses);
+                continue;
+            }
+            Message::Notification(not) => to_messages(handle_notification(not), None, true),
+            _ => to_messages(handle_other(msg), None, true),
         };
+
+        for message in messages {
+            if let Err(e) = connection.sender.send(message) {
+                error!("failed to send response: {:?}", e);
+            }
+        }
     }
 
     Ok(())
@@ -129,6 +366,8 @@ fn main_loop(connection: Connection, params: serde_json::Value) -> Result<()> {
 
 pub fn start_lsp() -> Result<()> {
     init_text_store();
+    init_workspace_index();
+    init_in_flight_requests();
 
     // Note that  we must have our logging only write out to stderr.
     info!("starting generic LSP server");
@@ -137,12 +376,32 @@ pub fn start_lsp() -> Result<()> {
     // also be implemented to use sockets or HTTP.
     let (connection, io_threads) = Connection::stdio();
 
+    // The client capabilities are needed to negotiate the position encoding
+    let (initialize_id, initialization_params) = connection.initialize_start()?;
+    let params: InitializeParams = serde_json::from_value(initialization_params.clone())?;
+    let position_encoding = init_position_encoding(&params);
+    init_config(&params);
+    init_snippet_support(&params);
+
     // Run the server and wait for the two threads to end (typically by trigger LSP Exit event).
     let server_capabilities = serde_json::to_value(ServerCapabilities {
-        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        position_encoding: Some(position_encoding.kind()),
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::INCREMENTAL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
         completion_provider: Some(lsp_types::CompletionOptions {
-            resolve_provider: Some(false),
-            trigger_characters: Some(vec!["-".to_string(), "\"".to_string(), " ".to_string()]),
+            resolve_provider: Some(true),
+            trigger_characters: Some(
+                ["-", "\"", " ", ":"]
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect(),
+            ),
             work_done_progress_options: WorkDoneProgressOptions {
                 work_done_progress: None,
             },
@@ -152,11 +411,24 @@ pub fn start_lsp() -> Result<()> {
 
         hover_provider: Some(lsp_types::HoverProviderCapability::Simple(true)),
 
+        definition_provider: Some(lsp_types::OneOf::Left(true)),
+
+        code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+            code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+            resolve_provider: None,
+        })),
+
         ..Default::default()
     })
     .unwrap();
 
-    let initialization_params = connection.initialize(server_capabilities)?;
+    connection.initialize_finish(
+        initialize_id,
+        serde_json::json!({ "capabilities": server_capabilities }),
+    )?;
     main_loop(connection, initialization_params)?;
     io_threads.join()?;
 
@@ -186,3 +458,53 @@ mod test {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lsp_server::{ErrorCode, Message, Notification, Request, RequestId};
+
+    use super::run_request;
+    use crate::{
+        cancel::{init_in_flight_requests, is_cancelled, track_message},
+        handle::HtmxResult,
+        scheduler::WorkerPool,
+    };
+
+    fn panicking_handler(_: Request) -> Option<HtmxResult> {
+        panic!("handler failed");
+    }
+
+    #[test]
+    fn test_it_answers_requests_whose_handler_panics() {
+        init_in_flight_requests();
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let req = Request {
+            id: 7101.into(),
+            method: "textDocument/hover".to_string(),
+            params: serde_json::Value::Null,
+        };
+        track_message(&Message::Request(req.clone()));
+
+        let pool = WorkerPool::new(1);
+        run_request(&pool, &sender, req, false, panicking_handler);
+        drop(pool);
+
+        match receiver.try_recv() {
+            Ok(Message::Response(response)) => {
+                assert_eq!(response.id, RequestId::from(7101));
+                assert_eq!(
+                    response.error.map(|error| error.code),
+                    Some(ErrorCode::InternalError as i32)
+                );
+            }
+            other => panic!("expected an error response, got {other:?}"),
+        }
+
+        // The request is no longer in flight, so cancelling it does nothing
+        track_message(&Message::Notification(Notification {
+            method: "$/cancelRequest".to_string(),
+            params: serde_json::json!({ "id": 7101 }),
+        }));
+        assert!(!is_cancelled(&RequestId::from(7101)));
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{mpsc, Arc, Mutex},
+    thread::JoinHandle,
+};
+
+use log::error;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed set of threads requests are handed to, so one slow request does not
+/// hold up the messages behind it
+pub struct WorkerPool {
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    pub fn new(size: usize) -> WorkerPool {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..size.max(1))
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                std::thread::spawn(move || loop {
+                    let job = match receiver.lock().expect("worker mutex poisoned").recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    // Requests answer their own panics, this only keeps the
+                    // worker alive if anything else in a job panics
+                    if std::panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        error!("worker job panicked");
+                    }
+                })
+            })
+            .collect();
+
+        WorkerPool {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    pub fn with_available_parallelism() -> WorkerPool {
+        let size = std::thread::available_parallelism()
+            .map(|n| n.get().min(4))
+            .unwrap_or(2);
+        WorkerPool::new(size)
+    }
+
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let sent = self
+            .sender
+            .as_ref()
+            .map(|sender| sender.send(Box::new(job)).is_ok());
+        if sent != Some(true) {
+            error!("worker pool is shut down, dropping job");
+        }
+    }
+}
+
+/// Waits for queued jobs to finish before the pool goes away
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                error!("worker thread panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::WorkerPool;
+
+    #[test]
+    fn test_it_runs_every_job_before_shutting_down() {
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let pool = WorkerPool::new(3);
+        for _ in 0..20 {
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        drop(pool);
+
+        assert_eq!(counter.load(Ordering::SeqCst), 20);
+    }
+}
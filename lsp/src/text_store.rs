@@ -1,12 +1,33 @@
 use std::{
+    cell::RefCell,
     collections::HashMap,
     ops::{Deref, DerefMut},
     sync::{Arc, Mutex, OnceLock},
 };
 
-use lsp_types::{TextDocumentPositionParams, Url};
+use htmx_lsp_util::{
+    byte_col_to_character, character_to_byte_col, get_line, get_text_byte_offset, PositionEncoding,
+};
+use log::{error, info};
+use lsp_types::{
+    InitializeParams, Position, Range, TextDocumentContentChangeEvent, TextDocumentPositionParams,
+    Url,
+};
+use tree_sitter::{InputEdit, Point, Tree};
+
+use crate::tree_sitter::parse_document;
+
+/// An open document along with its syntax tree, which is kept in sync with
+/// every change so features never have to parse from scratch. Clones share
+/// the text, which makes them cheap snapshots for requests running on worker
+/// threads.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub text: Arc<String>,
+    pub tree: Tree,
+}
 
-type TxtStore = HashMap<String, String>;
+type TxtStore = HashMap<String, Document>;
 
 pub struct TextStore(TxtStore);
 
@@ -29,7 +50,73 @@ pub fn init_text_store() {
     _ = TEXT_STORE.set(Arc::new(Mutex::new(TextStore(HashMap::new()))));
 }
 
-pub fn get_text_document(uri: &Url) -> Option<String> {
+pub static POSITION_ENCODING: OnceLock<PositionEncoding> = OnceLock::new();
+
+/// Picks the position encoding from the ones the client supports. UTF-8 is
+/// preferred since it matches the byte offsets tree-sitter works with.
+pub fn init_position_encoding(params: &InitializeParams) -> PositionEncoding {
+    let supported: Vec<_> = params
+        .capabilities
+        .general
+        .as_ref()
+        .and_then(|general| general.position_encodings.as_ref())
+        .map(|kinds| {
+            kinds
+                .iter()
+                .filter_map(PositionEncoding::from_kind)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let encoding = [PositionEncoding::Utf8, PositionEncoding::Utf32]
+        .into_iter()
+        .find(|encoding| supported.contains(encoding))
+        .unwrap_or_default();
+
+    info!("using position encoding {:?}", encoding);
+    _ = POSITION_ENCODING.set(encoding);
+
+    encoding
+}
+
+pub fn position_encoding() -> PositionEncoding {
+    POSITION_ENCODING.get().copied().unwrap_or_default()
+}
+
+thread_local! {
+    /// The document a request was received for, as it was at that moment
+    static SNAPSHOT: RefCell<Option<(String, Document)>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with `get_document` answering from `document` for `uri`, so work
+/// done on a worker thread sees the document the request was made against
+/// instead of edits that arrived in the meantime
+pub fn with_snapshot<T>(uri: &str, document: Document, f: impl FnOnce() -> T) -> T {
+    SNAPSHOT.with(|snapshot| *snapshot.borrow_mut() = Some((uri.to_string(), document)));
+    let _guard = SnapshotGuard;
+
+    f()
+}
+
+/// Clears the snapshot once the request is done, even when its handler
+/// panics, so the next request on the worker never sees a stale document
+struct SnapshotGuard;
+
+impl Drop for SnapshotGuard {
+    fn drop(&mut self) {
+        SNAPSHOT.with(|snapshot| *snapshot.borrow_mut() = None);
+    }
+}
+
+pub fn get_document(uri: &Url) -> Option<Document> {
+    let snapshot = SNAPSHOT.with(|snapshot| match &*snapshot.borrow() {
+        Some((snapshot_uri, document)) if snapshot_uri == uri.as_str() => Some(document.clone()),
+        _ => None,
+    });
+    if snapshot.is_some() {
+        return snapshot;
+    }
+
     return TEXT_STORE
         .get()
         .expect("text store not initialized")
@@ -39,47 +126,185 @@ pub fn get_text_document(uri: &Url) -> Option<String> {
         .cloned();
 }
 
-/// Find the start and end indices of a word inside the given line
+pub fn get_text_document(uri: &Url) -> Option<Arc<String>> {
+    get_document(uri).map(|document| document.text)
+}
+
+pub fn open_documents() -> Vec<(String, Document)> {
+    TEXT_STORE
+        .get()
+        .expect("text store not initialized")
+        .lock()
+        .expect("text store mutex poisoned")
+        .iter()
+        .map(|(uri, document)| (uri.clone(), document.clone()))
+        .collect()
+}
+
+pub fn remove_text_document(uri: &str) -> Option<Document> {
+    TEXT_STORE
+        .get()
+        .expect("text store not initialized")
+        .lock()
+        .expect("text store mutex poisoned")
+        .remove(uri)
+}
+
+/// The point right after `text` when it is inserted at `start`
+fn point_after(start: Point, text: &str) -> Point {
+    match text.rfind('\n') {
+        Some(last_newline) => Point::new(
+            start.row + text.matches('\n').count(),
+            text.len() - last_newline - 1,
+        ),
+        None => Point::new(start.row, start.column + text.len()),
+    }
+}
+
+/// The tree-sitter point of a byte offset, whose column is in bytes as well
+fn point_at(text: &str, offset: usize) -> Point {
+    let before = &text[..offset];
+    match before.rfind('\n') {
+        Some(last_newline) => Point::new(before.matches('\n').count(), offset - last_newline - 1),
+        None => Point::new(0, offset),
+    }
+}
+
+impl Document {
+    pub fn new(text: String) -> Document {
+        let tree = parse_document(&text, None).expect("parsing without a timeout never fails");
+        Document {
+            text: Arc::new(text),
+            tree,
+        }
+    }
+
+    /// Applies the changes of a didChange notification in order, editing the
+    /// tree alongside so it can be reparsed incrementally. Changes without a
+    /// range replace the whole document. A range outside the document means
+    /// we are out of sync with the client, so the whole notification is
+    /// rejected and the document stays as it was.
+    pub fn apply_changes(
+        &mut self,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> Result<(), String> {
+        let mut text = self.text.clone();
+        let mut tree = self.tree.clone();
+        let mut reuse_tree = true;
+
+        for change in changes {
+            let range = match change.range {
+                Some(range) => range,
+                None => {
+                    text = Arc::new(change.text);
+                    reuse_tree = false;
+                    continue;
+                }
+            };
+
+            let offset = |pos: lsp_types::Position| {
+                get_text_byte_offset(
+                    &text,
+                    pos.line as usize,
+                    pos.character as usize,
+                    position_encoding(),
+                )
+                .ok_or_else(|| format!("position {pos:?} is outside the document"))
+            };
+            let start = offset(range.start)?;
+            let end = offset(range.end)?;
+
+            if start > end {
+                return Err(format!("range {range:?} ends before it starts"));
+            }
+
+            let start_position = point_at(&text, start);
+            let old_end_position = point_at(&text, end);
+
+            Arc::make_mut(&mut text).replace_range(start..end, &change.text);
+
+            tree.edit(&InputEdit {
+                start_byte: start,
+                old_end_byte: end,
+                new_end_byte: start + change.text.len(),
+                start_position,
+                old_end_position,
+                new_end_position: point_after(start_position, &change.text),
+            });
+        }
+
+        let old_tree = reuse_tree.then_some(&tree);
+        self.tree = parse_document(&text, old_tree).expect("parsing without a timeout never fails");
+        self.text = text;
+
+        Ok(())
+    }
+}
+
+/// Applies the changes to the stored document and returns a snapshot of it
+pub fn update_text_document(
+    uri: &str,
+    changes: Vec<TextDocumentContentChangeEvent>,
+) -> Option<Document> {
+    let mut store = TEXT_STORE
+        .get()
+        .expect("text store not initialized")
+        .lock()
+        .expect("text store mutex poisoned");
+
+    let document = match store.get_mut(uri) {
+        Some(document) => document,
+        None => {
+            error!("update_text_document change for unknown document {uri}");
+            return None;
+        }
+    };
+
+    if let Err(err) = document.apply_changes(changes) {
+        error!("update_text_document rejected changes to {uri}: {err}");
+        return None;
+    }
+
+    Some(document.clone())
+}
+
+/// Find the start and end byte indices of a word inside the given line
 /// Borrowed from RLS
 fn find_word_at_pos(line: &str, col: usize) -> (usize, usize) {
-    let line_ = format!("{line} ");
     let is_ident_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
 
-    let start = line_
-        .chars()
-        .enumerate()
-        .take(col)
-        .filter(|&(_, c)| !is_ident_char(c))
-        .last()
-        .map(|(i, _)| i + 1)
+    let start = line[..col]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !is_ident_char(c))
+        .map(|(i, c)| i + c.len_utf8())
         .unwrap_or(0);
 
-    #[allow(clippy::filter_next)]
-    let mut end = line_
-        .chars()
-        .enumerate()
-        .skip(col)
-        .filter(|&(_, c)| !is_ident_char(c));
+    let end = line[col..]
+        .char_indices()
+        .find(|&(_, c)| !is_ident_char(c))
+        .map(|(i, _)| col + i)
+        .unwrap_or(line.len());
 
-    let end = end.next();
-    (start, end.map(|(i, _)| i).unwrap_or(col))
+    (start, end)
 }
 
 pub fn get_word_from_pos_params(pos_params: &TextDocumentPositionParams) -> anyhow::Result<String> {
     let uri = &pos_params.text_document.uri;
     let line = pos_params.position.line as usize;
-    let col = pos_params.position.character as usize;
+    let character = pos_params.position.character as usize;
 
     match get_text_document(uri) {
         Some(text) => {
-            let line_conts = match text.lines().nth(line) {
-                Some(conts) => conts,
+            let line_conts = match get_line(&text, line) {
+                Some((_, conts)) => conts,
                 None => {
                     return Err(anyhow::anyhow!(
                         "get_word_from_pos_params Failed to get word under cursor"
                     ));
                 }
             };
+            let col = character_to_byte_col(line_conts, character, position_encoding());
             let (start, end) = find_word_at_pos(line_conts, col);
             Ok(String::from(&line_conts[start..end]))
         }
@@ -88,3 +313,202 @@ pub fn get_word_from_pos_params(pos_params: &TextDocumentPositionParams) -> anyh
         )),
     }
 }
+
+/// The range from the start of the word being typed up to the cursor, which is
+/// what an accepted completion replaces
+pub fn get_typed_range(
+    pos_params: &TextDocumentPositionParams,
+    is_word_char: impl Fn(char) -> bool,
+) -> Option<Range> {
+    let text = get_text_document(&pos_params.text_document.uri)?;
+    let (_, line_conts) = get_line(&text, pos_params.position.line as usize)?;
+
+    let encoding = position_encoding();
+    let col = character_to_byte_col(line_conts, pos_params.position.character as usize, encoding);
+    let start = line_conts[..col]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word_char(c))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(col);
+
+    Some(Range::new(
+        Position::new(
+            pos_params.position.line,
+            byte_col_to_character(line_conts, start, encoding),
+        ),
+        Position::new(
+            pos_params.position.line,
+            byte_col_to_character(line_conts, col, encoding),
+        ),
+    ))
+}
+
+/// The rest of the line after the cursor
+pub fn get_text_after(pos_params: &TextDocumentPositionParams) -> Option<String> {
+    let text = get_text_document(&pos_params.text_document.uri)?;
+    let (_, line_conts) = get_line(&text, pos_params.position.line as usize)?;
+    let col = character_to_byte_col(
+        line_conts,
+        pos_params.position.character as usize,
+        position_encoding(),
+    );
+
+    Some(line_conts[col..].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_text_document, init_text_store, with_snapshot, Document, TEXT_STORE};
+    use crate::tree_sitter::parse_document;
+    use lsp_types::{Position, Range, TextDocumentContentChangeEvent, Url};
+
+    fn change(range: Option<(u32, u32, u32, u32)>, text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: range
+                .map(|(sl, sc, el, ec)| Range::new(Position::new(sl, sc), Position::new(el, ec))),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    fn assert_tree_is_up_to_date(document: &Document) {
+        let fresh = parse_document(&document.text, None).expect("not to fail");
+        assert_eq!(
+            document.tree.root_node().to_sexp(),
+            fresh.root_node().to_sexp()
+        );
+        assert_eq!(
+            document.tree.root_node().end_position(),
+            fresh.root_node().end_position()
+        );
+    }
+
+    #[test]
+    fn test_it_applies_range_changes_in_order() {
+        let mut document = Document::new("<div hx-get=\"/a\">\n</div>\n".to_string());
+
+        document
+            .apply_changes(vec![
+                change(Some((0, 13, 0, 15)), "/items"),
+                change(Some((1, 0, 1, 0)), "  <ul></ul>\n"),
+                change(Some((0, 4, 0, 4)), " hx-target=\"next ul\""),
+            ])
+            .expect("changes inside the document");
+
+        assert_eq!(
+            document.text.as_str(),
+            "<div hx-target=\"next ul\" hx-get=\"/items\">\n  <ul></ul>\n</div>\n"
+        );
+        assert_tree_is_up_to_date(&document);
+    }
+
+    #[test]
+    fn test_it_counts_characters_in_utf16_code_units() {
+        let mut document = Document::new("<p>Grüße 👋 hx</p>".to_string());
+
+        // "👋" takes two UTF-16 code units
+        document
+            .apply_changes(vec![change(Some((0, 12, 0, 14)), "htmx")])
+            .expect("changes inside the document");
+
+        assert_eq!(document.text.as_str(), "<p>Grüße 👋 htmx</p>");
+        assert_tree_is_up_to_date(&document);
+    }
+
+    #[test]
+    fn test_it_replaces_everything_without_a_range() {
+        let mut document = Document::new("old".to_string());
+
+        document
+            .apply_changes(vec![
+                change(None, "<div>\ntext"),
+                change(Some((1, 0, 1, 4)), "<span hx-get=\"/\"></span>"),
+            ])
+            .expect("changes inside the document");
+
+        assert_eq!(document.text.as_str(), "<div>\n<span hx-get=\"/\"></span>");
+        assert_tree_is_up_to_date(&document);
+    }
+
+    #[test]
+    fn test_it_rejects_changes_outside_the_document() {
+        let text = "<div hx-get=\"/a\">\n</div>\n";
+        let mut document = Document::new(text.to_string());
+
+        let result = document.apply_changes(vec![
+            change(Some((0, 1, 0, 4)), "span"),
+            change(Some((7, 0, 7, 1)), "x"),
+        ]);
+
+        assert!(result.is_err());
+        assert_eq!(document.text.as_str(), text);
+        assert_tree_is_up_to_date(&document);
+    }
+
+    #[test]
+    fn test_it_reparses_edits_spanning_lines() {
+        let mut document =
+            Document::new("<ul>\n  <li hx-get=\"/1\">1</li>\n  <li>2</li>\n</ul>".to_string());
+
+        document
+            .apply_changes(vec![change(Some((1, 5, 2, 5)), " id=\"a\">1</li>\n  <li")])
+            .expect("changes inside the document");
+
+        assert_eq!(
+            document.text.as_str(),
+            "<ul>\n  <li id=\"a\">1</li>\n  <li>2</li>\n</ul>"
+        );
+        assert_tree_is_up_to_date(&document);
+    }
+
+    #[test]
+    fn test_it_answers_from_the_snapshot_on_this_thread_only() {
+        let uri = "file:///snapshot.html";
+        init_text_store();
+        TEXT_STORE
+            .get()
+            .expect("text store not initialized")
+            .lock()
+            .expect("text store mutex poisoned")
+            .insert(uri.to_string(), Document::new("<p>new</p>".to_string()));
+
+        let url = Url::parse(uri).expect("valid uri");
+        let text = with_snapshot(uri, Document::new("<p>old</p>".to_string()), || {
+            let from_other_thread = std::thread::spawn({
+                let url = url.clone();
+                move || get_text_document(&url)
+            })
+            .join()
+            .expect("thread to finish");
+            assert_eq!(
+                from_other_thread.as_deref().map(String::as_str),
+                Some("<p>new</p>")
+            );
+
+            get_text_document(&url)
+        });
+
+        assert_eq!(text.as_deref().map(String::as_str), Some("<p>old</p>"));
+        assert_eq!(
+            get_text_document(&url).as_deref().map(String::as_str),
+            Some("<p>new</p>")
+        );
+    }
+
+    #[test]
+    fn test_it_clears_the_snapshot_when_the_request_panics() {
+        let uri = "file:///panicking-snapshot.html";
+        init_text_store();
+
+        let result = std::panic::catch_unwind(|| {
+            with_snapshot(uri, Document::new("<p>stale</p>".to_string()), || {
+                panic!("handler failed")
+            })
+        });
+
+        assert!(result.is_err());
+        assert!(get_text_document(&Url::parse(uri).expect("valid uri")).is_none());
+    }
+}
@@ -1,11 +1,14 @@
 use crate::tree_sitter_querier::{
-    query_attr_keys_for_completion, query_attr_values_for_completion,
+    query_attr_keys_for_completion, query_attr_values_for_completion, query_htmx_attributes,
+    HtmxAttribute,
 };
 use log::{debug, error};
 use lsp_types::TextDocumentPositionParams;
-use tree_sitter::{Node, Parser, Point};
+use tree_sitter::{Node, Parser, Point, Tree};
 
-use crate::text_store::get_text_document;
+use crate::htmx::prefix::{attribute_prefixes, split_prefix};
+use crate::text_store::{get_document, position_encoding};
+use htmx_lsp_util::{byte_col_to_character, character_to_byte_col, get_line};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Position {
@@ -89,6 +92,51 @@ fn query_position(root: Node<'_>, source: &str, trigger_point: Point) -> Option<
     query_attr_values_for_completion(element, source, trigger_point)
 }
 
+/// The attribute names written in the tag around `trigger_point`, leaving out
+/// the attribute the cursor is in
+fn query_sibling_attributes(root: Node<'_>, source: &str, trigger_point: Point) -> Vec<String> {
+    let is_tag =
+        |node: &Node<'_>| matches!(node.kind(), "start_tag" | "self_closing_tag" | "ERROR");
+
+    let mut node = root.descendant_for_point_range(trigger_point, trigger_point);
+    while let Some(current) = node {
+        if is_tag(&current) {
+            break;
+        }
+
+        // The end of an unfinished tag resolves to its element
+        if let Some(tag) = current.named_child(0).filter(|tag| {
+            is_tag(tag)
+                && tag.start_position() <= trigger_point
+                && trigger_point <= tag.end_position()
+        }) {
+            node = Some(tag);
+            break;
+        }
+
+        node = current.parent();
+    }
+    let tag = match node {
+        Some(tag) => tag,
+        None => return vec![],
+    };
+
+    let mut cursor = tag.walk();
+    tag.named_children(&mut cursor)
+        .filter(|child| {
+            child.start_position() > trigger_point || child.end_position() < trigger_point
+        })
+        .filter_map(|child| match child.kind() {
+            "attribute" => child.named_child(0),
+            "attribute_name" => Some(child),
+            _ => None,
+        })
+        .filter(|name| name.kind() == "attribute_name")
+        .filter_map(|name| name.utf8_text(source.as_bytes()).ok())
+        .map(String::from)
+        .collect()
+}
+
 // TODO: remove if not used
 #[allow(dead_code)]
 fn get_position(root: Node<'_>, source: &str, row: usize, column: usize) -> Option<Position> {
@@ -101,32 +149,115 @@ fn get_position(root: Node<'_>, source: &str, row: usize, column: usize) -> Opti
     create_attribute(desc, source)
 }
 
+/// Parses `text`, reusing the unchanged parts of `old_tree` when it has been
+/// edited to match
+pub fn parse_document(text: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+    let mut parser = Parser::new();
+
+    parser
+        .set_language(tree_sitter_html::language())
+        .expect("could not load html grammer");
+
+    parser.parse(text, old_tree)
+}
+
+/// Tree-sitter columns count bytes, LSP characters count units of the
+/// negotiated position encoding
+pub fn point_to_lsp_position(source: &str, point: Point) -> lsp_types::Position {
+    let line = get_line(source, point.row)
+        .map(|(_, line)| line)
+        .unwrap_or("");
+
+    lsp_types::Position {
+        line: point.row as u32,
+        character: byte_col_to_character(line, point.column, position_encoding()),
+    }
+}
+
+pub fn lsp_position_to_point(source: &str, position: lsp_types::Position) -> Point {
+    let line = get_line(source, position.line as usize)
+        .map(|(_, line)| line)
+        .unwrap_or("");
+
+    Point::new(
+        position.line as usize,
+        character_to_byte_col(line, position.character as usize, position_encoding()),
+    )
+}
+
+pub fn range_to_lsp_range(source: &str, range: tree_sitter::Range) -> lsp_types::Range {
+    lsp_types::Range {
+        start: point_to_lsp_position(source, range.start_point),
+        end: point_to_lsp_position(source, range.end_point),
+    }
+}
+
 pub fn get_position_from_lsp_completion(
     text_params: TextDocumentPositionParams,
 ) -> Option<Position> {
     error!("get_position_from_lsp_completion");
-    let text = get_text_document(&text_params.text_document.uri)?;
-    error!("get_position_from_lsp_completion: text {}", text);
+    let document = get_document(&text_params.text_document.uri)?;
+    error!("get_position_from_lsp_completion: text {}", document.text);
     let pos = text_params.position;
     error!("get_position_from_lsp_completion: pos {:?}", pos);
 
-    // TODO: Gallons of perf work can be done starting here
-    let mut parser = Parser::new();
+    let root_node = document.tree.root_node();
+    let trigger_point = lsp_position_to_point(&document.text, pos);
 
-    parser
-        .set_language(tree_sitter_html::language())
-        .expect("could not load html grammer");
+    query_position(root_node, document.text.as_str(), trigger_point)
+}
 
-    let tree = parser.parse(&text, None)?;
-    let root_node = tree.root_node();
-    let trigger_point = Point::new(pos.line as usize, pos.character as usize);
+/// The canonical names of the htmx attributes already on the element being
+/// completed, not counting the one being typed
+pub fn get_sibling_attributes(text_params: TextDocumentPositionParams) -> Vec<String> {
+    let document = match get_document(&text_params.text_document.uri) {
+        Some(document) => document,
+        None => return vec![],
+    };
+    let trigger_point = lsp_position_to_point(&document.text, text_params.position);
+    let prefixes = attribute_prefixes();
+
+    query_sibling_attributes(document.tree.root_node(), &document.text, trigger_point)
+        .iter()
+        .filter_map(|name| split_prefix(name, &prefixes).map(|(_, canonical)| canonical))
+        .collect()
+}
+
+/// The htmx attribute whose value contains the cursor, along with the byte
+/// offset of the cursor inside that value
+pub fn get_attribute_value_at_position(
+    text_params: TextDocumentPositionParams,
+) -> Option<(HtmxAttribute, usize)> {
+    let document = get_document(&text_params.text_document.uri)?;
+    let pos = text_params.position;
+    let trigger_point = lsp_position_to_point(&document.text, pos);
+
+    query_htmx_attributes(document.tree.root_node(), &document.text)
+        .into_iter()
+        .find_map(|attribute| {
+            let offset = attribute.value.as_ref()?.offset_at(trigger_point)?;
+            Some((attribute, offset))
+        })
+}
 
-    query_position(root_node, text.as_str(), trigger_point)
+/// The htmx attribute whose name contains the cursor
+pub fn get_attribute_name_at_position(
+    text_params: TextDocumentPositionParams,
+) -> Option<HtmxAttribute> {
+    let document = get_document(&text_params.text_document.uri)?;
+    let trigger_point = lsp_position_to_point(&document.text, text_params.position);
+
+    query_htmx_attributes(document.tree.root_node(), &document.text)
+        .into_iter()
+        .find(|attribute| {
+            attribute.name_range.start_point <= trigger_point
+                && trigger_point <= attribute.name_range.end_point
+        })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{get_position, query_position, Position};
+    use super::{get_position, query_position, query_sibling_attributes, Position};
     use tree_sitter::{Parser, Point};
 
     fn prepare_tree(text: &str) -> tree_sitter::Tree {
@@ -242,6 +373,25 @@ mod tests {
         assert_eq!(matches, Some(Position::AttributeName("hx-".to_string())));
     }
 
+    #[test]
+    fn test_it_finds_the_other_attributes_of_the_tag() {
+        let text = r#"<div hx-get="/a" hx-target="x">
+      <span data-hx-post="/b" hx-
+      <button>Click me</button>
+    </div>"#;
+
+        let tree = prepare_tree(text);
+
+        assert_eq!(
+            query_sibling_attributes(tree.root_node(), text, Point::new(1, 33)),
+            vec!["data-hx-post"]
+        );
+        assert_eq!(
+            query_sibling_attributes(tree.root_node(), text, Point::new(0, 8)),
+            vec!["hx-target"]
+        );
+    }
+
     #[test]
     fn test_it_matches_more_than_one_attribute() {
         let text = r#"<div hx-get="/foo" hx-target="this" hx- ></div>"#;
@@ -1,11 +1,17 @@
 // See: https://tree-sitter.github.io/tree-sitter/using-parsers#query-syntax
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
 
 use log::{debug, error};
-use tree_sitter::{Node, Point, Query, QueryCursor};
+use tree_sitter::{Node, Point, Query, QueryCursor, Range};
 
-use crate::tree_sitter::Position;
+use crate::{
+    htmx::prefix::{attribute_prefixes, prefix_pattern, split_prefix, with_prefix},
+    tree_sitter::Position,
+};
 
 // If error char is "=" means the key name is completed and the cursor is
 // at the "=" but no quote, so we shouldn't suggest yet eg <div hx-foo=|>
@@ -17,14 +23,52 @@ struct CaptureDetails {
     end_position: Point,
 }
 
+/// The queries run against documents, each compiled once and kept until its
+/// text changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum QueryKind {
+    AttributeKeys,
+    AttributeValues,
+    HtmxAttributes,
+    ElementIds,
+    ScriptSources,
+}
+
+/// The text of each kind of query along with the query compiled from it
+type CompiledQueries = HashMap<QueryKind, (String, Arc<Query>)>;
+
+/// The compiled query of `kind`. Some queries embed the configured prefixes,
+/// so the one kept for a kind is replaced when its text changes instead of
+/// piling up a query per prefix setting.
+fn compiled_query(kind: QueryKind, query_string: &str) -> Arc<Query> {
+    static QUERIES: OnceLock<Mutex<CompiledQueries>> = OnceLock::new();
+
+    let mut queries = QUERIES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .expect("compiled queries mutex poisoned");
+
+    match queries.get(&kind) {
+        Some((text, query)) if text == query_string => Arc::clone(query),
+        _ => {
+            let query = Arc::new(
+                Query::new(tree_sitter_html::language(), query_string)
+                    .unwrap_or_else(|_| panic!("compiled_query invalid query {query_string}")),
+            );
+            queries.insert(kind, (query_string.to_string(), Arc::clone(&query)));
+            query
+        }
+    }
+}
+
 fn query_props(
+    kind: QueryKind,
     query_string: &str,
     node: Node<'_>,
     source: &str,
     trigger_point: Point,
 ) -> HashMap<String, CaptureDetails> {
-    let query = Query::new(tree_sitter_html::language(), query_string)
-        .unwrap_or_else(|_| panic!("get_position_by_query invalid query {query_string}"));
+    let query = compiled_query(kind, query_string);
     let mut cursor_qry = QueryCursor::new();
 
     let capture_names = query.capture_names();
@@ -67,7 +111,8 @@ pub fn query_attr_keys_for_completion(
     trigger_point: Point,
 ) -> Option<Position> {
     // [ ] means match any of the following
-    let query_string = r#"
+    let query_string = format!(
+        r#"
     (
         [
             (_ 
@@ -89,10 +134,18 @@ pub fn query_attr_keys_for_completion(
             ) @unfinished_tag
         ]
 
-        (#match? @attr_name "hx-.*")
-    )"#;
-
-    let props = query_props(query_string, node, source, trigger_point);
+        (#match? @attr_name "{}")
+    )"#,
+        prefix_pattern(&attribute_prefixes())
+    );
+
+    let props = query_props(
+        QueryKind::AttributeKeys,
+        &query_string,
+        node,
+        source,
+        trigger_point,
+    );
     let attr_name = props.get("attr_name")?;
 
     if props.contains_key("unfinished_tag") {
@@ -108,7 +161,8 @@ pub fn query_attr_values_for_completion(
     trigger_point: Point,
 ) -> Option<Position> {
     // [ ] means match any of the following
-    let query_string = r#"(
+    let query_string = format!(
+        r#"(
         [
           (ERROR 
             (tag_name) 
@@ -150,10 +204,18 @@ pub fn query_attr_values_for_completion(
           )
         ]
 
-        (#match? @attr_name "hx-.*")
-    )"#;
+        (#match? @attr_name "{}")
+    )"#,
+        prefix_pattern(&attribute_prefixes())
+    );
 
-    let props = query_props(query_string, node, source, trigger_point);
+    let props = query_props(
+        QueryKind::AttributeValues,
+        &query_string,
+        node,
+        source,
+        trigger_point,
+    );
 
     let attr_name = props.get("attr_name")?;
 
@@ -183,3 +245,256 @@ pub fn query_attr_values_for_completion(
         value: "".to_string(),
     })
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeValue {
+    pub value: String,
+    /// Range of the value without the surrounding quotes
+    pub range: Range,
+}
+
+impl AttributeValue {
+    fn point_at(&self, offset: usize) -> Point {
+        let mut point = self.range.start_point;
+
+        for c in self.value[..offset].chars() {
+            if c == '\n' {
+                point.row += 1;
+                point.column = 0;
+            } else {
+                point.column += c.len_utf8();
+            }
+        }
+
+        point
+    }
+
+    /// Converts a document point into a byte offset relative to the value
+    pub fn offset_at(&self, point: Point) -> Option<usize> {
+        if point < self.range.start_point || point > self.range.end_point {
+            return None;
+        }
+
+        let mut current = self.range.start_point;
+        for (idx, c) in self.value.char_indices() {
+            if current >= point {
+                return Some(idx);
+            }
+
+            if c == '\n' {
+                current.row += 1;
+                current.column = 0;
+            } else {
+                current.column += c.len_utf8();
+            }
+        }
+
+        Some(self.value.len())
+    }
+
+    /// Converts a byte range relative to the value into a document range
+    pub fn sub_range(&self, range: std::ops::Range<usize>) -> Range {
+        let start = range.start.min(self.value.len());
+        let end = range.end.clamp(start, self.value.len());
+
+        Range {
+            start_byte: self.range.start_byte + start,
+            end_byte: self.range.start_byte + end,
+            start_point: self.point_at(start),
+            end_point: self.point_at(end),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmxAttribute {
+    /// The canonical `hx-` name, whichever prefix the attribute is written with
+    pub name: String,
+    /// The prefix as written in the document, e.g. `hx-` or `data-hx-`
+    pub prefix: String,
+    pub name_range: Range,
+    pub value: Option<AttributeValue>,
+}
+
+impl HtmxAttribute {
+    /// The name as it is written in the document
+    pub fn written_name(&self) -> String {
+        with_prefix(&self.prefix, &self.name)
+    }
+
+    /// The `attribute` node the attribute was queried from
+    pub fn node<'a>(&self, root: Node<'a>) -> Option<Node<'a>> {
+        root.descendant_for_point_range(self.name_range.start_point, self.name_range.end_point)?
+            .parent()
+            .filter(|node| node.kind() == "attribute")
+    }
+}
+
+fn attribute_value_from_node(node: Node<'_>, source: &str) -> Option<AttributeValue> {
+    let value_node = match node.kind() {
+        "attribute_value" => node,
+        "quoted_attribute_value" => match node.named_child(0) {
+            Some(value) => value,
+            // An empty "" value has no inner node, so point at the gap between the quotes
+            None => {
+                let start_byte = node.start_byte() + 1;
+                let start_point =
+                    Point::new(node.start_position().row, node.start_position().column + 1);
+                return Some(AttributeValue {
+                    value: "".to_string(),
+                    range: Range {
+                        start_byte,
+                        end_byte: start_byte,
+                        start_point,
+                        end_point: start_point,
+                    },
+                });
+            }
+        },
+        _ => return None,
+    };
+
+    Some(AttributeValue {
+        value: value_node.utf8_text(source.as_bytes()).ok()?.to_owned(),
+        range: value_node.range(),
+    })
+}
+
+fn query_attributes(
+    kind: QueryKind,
+    root: Node<'_>,
+    source: &str,
+    query_string: &str,
+) -> Vec<HtmxAttribute> {
+    let query = compiled_query(kind, query_string);
+    let attribute_idx = query
+        .capture_index_for_name("attribute")
+        .expect("attribute capture to exist");
+    let mut cursor_qry = QueryCursor::new();
+
+    cursor_qry
+        .matches(&query, root, source.as_bytes())
+        .filter_map(|m| {
+            let attribute = m
+                .captures
+                .iter()
+                .find(|capture| capture.index == attribute_idx)?
+                .node;
+            let name_node = attribute.named_child(0)?;
+
+            Some(HtmxAttribute {
+                name: name_node.utf8_text(source.as_bytes()).ok()?.to_owned(),
+                prefix: "".to_string(),
+                name_range: name_node.range(),
+                value: attribute
+                    .named_child(1)
+                    .and_then(|value| attribute_value_from_node(value, source)),
+            })
+        })
+        .collect()
+}
+
+/// Collects every htmx attribute in the tree along with its (unquoted) value.
+/// Names are canonicalized, so `data-hx-get` is reported as `hx-get`.
+pub fn query_htmx_attributes(root: Node<'_>, source: &str) -> Vec<HtmxAttribute> {
+    let prefixes = attribute_prefixes();
+    let query_string = format!(
+        r#"(
+        (attribute (attribute_name) @attr_name) @attribute
+
+        (#match? @attr_name "{}")
+    )"#,
+        prefix_pattern(&prefixes)
+    );
+
+    query_attributes(QueryKind::HtmxAttributes, root, source, &query_string)
+        .into_iter()
+        .filter_map(|attribute| {
+            let (prefix, name) = split_prefix(&attribute.name, &prefixes)?;
+            Some(HtmxAttribute {
+                prefix: prefix.to_string(),
+                name,
+                ..attribute
+            })
+        })
+        .collect()
+}
+
+/// The extensions `hx-ext` enables on the element containing `point` and its
+/// ancestors, minus the ones a closer element drops with `ignore:`
+pub fn query_enabled_extensions(root: Node<'_>, source: &str, point: Point) -> Vec<String> {
+    let prefixes = attribute_prefixes();
+    let mut enabled: Vec<String> = vec![];
+    let mut ignored: Vec<String> = vec![];
+    let mut node = root.descendant_for_point_range(point, point);
+
+    while let Some(current) = node {
+        node = current.parent();
+
+        let tag = match current.kind() {
+            "element" => current.named_child(0),
+            _ => None,
+        };
+        let tag = match tag {
+            Some(tag) if matches!(tag.kind(), "start_tag" | "self_closing_tag") => tag,
+            _ => continue,
+        };
+
+        let mut cursor = tag.walk();
+        let values = tag
+            .named_children(&mut cursor)
+            .filter(|attribute| attribute.kind() == "attribute")
+            .filter(|attribute| {
+                attribute
+                    .named_child(0)
+                    .and_then(|name| name.utf8_text(source.as_bytes()).ok())
+                    .and_then(|name| split_prefix(name, &prefixes))
+                    .is_some_and(|(_, name)| name == "hx-ext")
+            })
+            .filter_map(|attribute| attribute_value_from_node(attribute.named_child(1)?, source));
+
+        for value in values {
+            for extension in value.value.split(',').map(str::trim) {
+                match extension.strip_prefix("ignore:") {
+                    Some(extension) => ignored.push(extension.trim().to_string()),
+                    None if extension.is_empty()
+                        || ignored.iter().any(|name| name == extension)
+                        || enabled.iter().any(|name| name == extension) => {}
+                    None => enabled.push(extension.to_string()),
+                }
+            }
+        }
+    }
+
+    enabled
+}
+
+/// Collects the value of every `id` attribute in the tree
+pub fn query_element_ids(root: Node<'_>, source: &str) -> Vec<AttributeValue> {
+    let query_string = r#"(
+        (attribute (attribute_name) @attr_name) @attribute
+
+        (#eq? @attr_name "id")
+    )"#;
+
+    query_attributes(QueryKind::ElementIds, root, source, query_string)
+        .into_iter()
+        .filter_map(|attribute| attribute.value)
+        .filter(|value| !value.value.is_empty())
+        .collect()
+}
+
+/// Collects the `src` of every script tag in the tree
+pub fn query_script_sources(root: Node<'_>, source: &str) -> Vec<AttributeValue> {
+    let query_string = r#"(
+        (script_element
+          (start_tag (attribute (attribute_name) @attr_name) @attribute))
+
+        (#eq? @attr_name "src")
+    )"#;
+
+    query_attributes(QueryKind::ScriptSources, root, source, query_string)
+        .into_iter()
+        .filter_map(|attribute| attribute.value)
+        .collect()
+}
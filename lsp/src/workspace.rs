@@ -0,0 +1,369 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
+
+use log::{debug, error, info};
+use lsp_types::{InitializeParams, Location, Range, Url};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{
+    config::config,
+    custom_data::load_custom_data,
+    htmx::{
+        selector::id_matches,
+        version::{detect_document_version, forget_document_version},
+    },
+    text_store::Document,
+    tree_sitter::range_to_lsp_range,
+    tree_sitter_querier::query_element_ids,
+};
+
+/// File extensions that are scanned for elements when indexing the workspace
+pub const TEMPLATE_EXTENSIONS: &[&str] = &[
+    "html",
+    "htm",
+    "shtml",
+    "xhtml",
+    "jinja",
+    "jinja2",
+    "j2",
+    "njk",
+    "hbs",
+    "handlebars",
+    "mustache",
+    "tmpl",
+    "tpl",
+    "gohtml",
+    "templ",
+    "erb",
+    "ejs",
+    "twig",
+    "php",
+    "djhtml",
+    "liquid",
+];
+
+const IGNORED_DIRECTORIES: &[&str] = &["node_modules", "target", "vendor", "dist", "build"];
+
+/// An `id` attribute value and where it sits in its document
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementId {
+    pub id: String,
+    pub range: Range,
+}
+
+/// The ids declared in each document, keyed by uri
+type IdIndex = HashMap<String, Vec<ElementId>>;
+
+pub struct WorkspaceIndex(IdIndex);
+
+impl Deref for WorkspaceIndex {
+    type Target = IdIndex;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for WorkspaceIndex {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+pub static WORKSPACE_INDEX: OnceLock<Arc<Mutex<WorkspaceIndex>>> = OnceLock::new();
+
+static WORKSPACE_ROOTS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Number of workspace folders still being scanned
+static PENDING_SCANS: AtomicUsize = AtomicUsize::new(0);
+
+type ScanListener = Box<dyn Fn() + Send + Sync>;
+
+/// Called on the scanning thread once the last pending scan is over
+static SCANS_FINISHED: OnceLock<ScanListener> = OnceLock::new();
+
+pub fn init_workspace_index() {
+    _ = WORKSPACE_INDEX.set(Arc::new(Mutex::new(WorkspaceIndex(HashMap::new()))));
+}
+
+fn lock_index() -> std::sync::MutexGuard<'static, WorkspaceIndex> {
+    WORKSPACE_INDEX
+        .get()
+        .expect("workspace index not initialized")
+        .lock()
+        .expect("workspace index mutex poisoned")
+}
+
+pub fn document_ids(document: &Document) -> Vec<ElementId> {
+    query_element_ids(document.tree.root_node(), &document.text)
+        .into_iter()
+        .map(|value| ElementId {
+            range: range_to_lsp_range(&document.text, value.range),
+            id: value.value,
+        })
+        .collect()
+}
+
+/// Indexes the ids of a document, returning whether they differ from the
+/// ones indexed before, which other documents may refer to
+pub fn index_document(uri: &str, document: &Document) -> bool {
+    let ids = document_ids(document);
+    detect_document_version(uri, document);
+
+    let previous = lock_index().insert(uri.to_string(), ids.clone());
+    !same_ids(previous.as_deref().unwrap_or_default(), &ids)
+}
+
+fn same_ids(a: &[ElementId], b: &[ElementId]) -> bool {
+    let names = |ids: &[ElementId]| {
+        let mut names: Vec<_> = ids.iter().map(|element| element.id.clone()).collect();
+        names.sort();
+        names
+    };
+
+    names(a) == names(b)
+}
+
+/// Once the client closes a document its content on disk takes over from the
+/// editor buffer, documents that are not workspace templates leave the index.
+/// Returns whether the indexed ids changed.
+pub fn reindex_from_disk(uri: &str) -> bool {
+    let text = Url::parse(uri)
+        .ok()
+        .and_then(|uri| uri.to_file_path().ok())
+        .filter(|path| is_template(path, &config().template_globs))
+        .and_then(|path| std::fs::read_to_string(path).ok());
+
+    match text {
+        Some(text) => index_document(uri, &Document::new(text)),
+        None => {
+            forget_document_version(uri);
+            lock_index()
+                .remove(uri)
+                .is_some_and(|previous| !previous.is_empty())
+        }
+    }
+}
+
+fn is_ignored(entry: &DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && (name.starts_with('.') || IGNORED_DIRECTORIES.contains(&name.as_ref()))
+}
+
+/// Matches `*` within a path segment, `**` across segments and `?` for a
+/// single character
+fn glob_matches(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            glob_matches(rest, path)
+                || (0..path.len())
+                    .filter(|idx| path[*idx] == '/')
+                    .any(|idx| glob_matches(rest, &path[idx + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|idx| glob_matches(rest, &path[idx..])),
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|idx| *idx == 0 || path[idx - 1] != '/')
+            .any(|idx| glob_matches(rest, &path[idx..])),
+        ['?', rest @ ..] => {
+            matches!(path.first(), Some(c) if *c != '/') && glob_matches(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && glob_matches(rest, &path[1..]),
+    }
+}
+
+/// Relative globs match anywhere in the workspace, like gitignore patterns
+fn matches_glob(glob: &str, path: &Path) -> bool {
+    let path: Vec<char> = path.to_string_lossy().replace('\\', "/").chars().collect();
+    let glob = match glob.starts_with('/') {
+        true => glob.to_string(),
+        false => format!("**/{glob}"),
+    };
+
+    glob_matches(&glob.chars().collect::<Vec<_>>(), &path)
+}
+
+fn is_template(path: &Path, template_globs: &[String]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| TEMPLATE_EXTENSIONS.contains(&ext))
+        .unwrap_or(false)
+        || template_globs.iter().any(|glob| matches_glob(glob, path))
+}
+
+fn index_directory(root: &Path) {
+    info!("indexing workspace {:?}", root);
+    let config = config();
+    let template_globs = &config.template_globs;
+
+    let templates = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| !is_ignored(entry))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && is_template(entry.path(), template_globs));
+
+    for entry in templates {
+        let uri = match Url::from_file_path(entry.path()) {
+            Ok(uri) => uri.to_string(),
+            Err(_) => continue,
+        };
+
+        // Documents opened by the client are kept up to date through didChange
+        if lock_index().contains_key(&uri) {
+            continue;
+        }
+
+        let text = match std::fs::read_to_string(entry.path()) {
+            Ok(text) => text,
+            Err(err) => {
+                debug!(
+                    "index_directory failed to read {:?}: {:?}",
+                    entry.path(),
+                    err
+                );
+                continue;
+            }
+        };
+
+        let document = Document::new(text);
+        let ids = document_ids(&document);
+        if let Entry::Vacant(entry) = lock_index().entry(uri) {
+            detect_document_version(entry.key(), &document);
+            entry.insert(ids);
+        }
+    }
+
+    info!("finished indexing workspace {:?}", root);
+}
+
+fn workspace_roots(params: &InitializeParams) -> Vec<Url> {
+    match &params.workspace_folders {
+        Some(folders) if !folders.is_empty() => {
+            folders.iter().map(|folder| folder.uri.clone()).collect()
+        }
+        #[allow(deprecated)]
+        _ => params.root_uri.iter().cloned().collect(),
+    }
+}
+
+/// Scans the workspace folders in the background so the initialize handshake
+/// is never held up by large repositories
+pub fn index_workspace(params: &InitializeParams) {
+    let roots = workspace_roots(params)
+        .into_iter()
+        .filter_map(|root| match root.to_file_path() {
+            Ok(path) => Some(path),
+            Err(_) => {
+                error!("index_workspace unsupported workspace uri {root}");
+                None
+            }
+        })
+        .collect();
+
+    _ = WORKSPACE_ROOTS.set(roots);
+    load_custom_data(&workspace_root_paths());
+    rescan_workspace();
+}
+
+/// Registers what to do once every id of the workspace is known, e.g.
+/// diagnose the open documents again
+pub fn on_scans_finished(listener: impl Fn() + Send + Sync + 'static) {
+    _ = SCANS_FINISHED.set(Box::new(listener));
+}
+
+pub fn workspace_root_paths() -> Vec<PathBuf> {
+    WORKSPACE_ROOTS.get().cloned().unwrap_or_default()
+}
+
+/// Scans the workspace folders again, picking up files that the template
+/// globs of a new configuration match
+pub fn rescan_workspace() {
+    for path in workspace_root_paths() {
+        PENDING_SCANS.fetch_add(1, Ordering::SeqCst);
+        std::thread::spawn(move || {
+            index_directory(&path);
+
+            if PENDING_SCANS.fetch_sub(1, Ordering::SeqCst) == 1 {
+                if let Some(listener) = SCANS_FINISHED.get() {
+                    listener();
+                }
+            }
+        });
+    }
+}
+
+pub fn is_indexing() -> bool {
+    PENDING_SCANS.load(Ordering::SeqCst) > 0
+}
+
+/// Whether any indexed document declares an element that `#id` can refer to
+pub fn id_exists_in_workspace(id: &str) -> bool {
+    WORKSPACE_INDEX
+        .get()
+        .map(|index| {
+            index
+                .lock()
+                .expect("workspace index mutex poisoned")
+                .values()
+                .flatten()
+                .any(|declared| id_matches(&declared.id, id))
+        })
+        .unwrap_or(false)
+}
+
+/// Every element with the given id, in documents other than `exclude_uri`
+pub fn find_id_in_workspace(id: &str, exclude_uri: &str) -> Vec<Location> {
+    lock_index()
+        .iter()
+        .filter(|(uri, _)| uri.as_str() != exclude_uri)
+        .flat_map(|(uri, ids)| {
+            let uri = Url::parse(uri).ok();
+            ids.iter()
+                .filter(|element| element.id == id)
+                .filter_map(move |element| {
+                    Some(Location {
+                        uri: uri.clone()?,
+                        range: element.range,
+                    })
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{is_template, matches_glob};
+
+    #[test]
+    fn test_template_globs() {
+        let path = Path::new("/home/me/project/templates/partials/row.txt");
+
+        assert!(matches_glob("*.txt", path));
+        assert!(matches_glob("templates/**/*.txt", path));
+        assert!(matches_glob("partials/ro?.txt", path));
+        assert!(matches_glob("/home/**/row.*", path));
+        assert!(!matches_glob("templates/*.txt", path));
+        assert!(!matches_glob("/project/**/*.txt", path));
+    }
+
+    #[test]
+    fn test_is_template() {
+        let globs = vec!["*.txt".to_string()];
+
+        assert!(is_template(Path::new("/p/index.html"), &[]));
+        assert!(!is_template(Path::new("/p/notes.txt"), &[]));
+        assert!(is_template(Path::new("/p/notes.txt"), &globs));
+        assert!(!is_template(Path::new("/p/main.rs"), &globs));
+    }
+}
@@ -1,46 +1,167 @@
-use lsp_types::Position;
+use lsp_types::{Position, PositionEncodingKind};
 
-pub fn byte_pos_to_line_col(source: &str, byte_pos: usize) -> Position {
-    let mut line = 0;
-    let mut col: u32 = 0;
+/// The unit LSP `character` offsets are counted in, negotiated at initialize.
+/// Clients that do not negotiate use UTF-16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+    Utf8,
+    #[default]
+    Utf16,
+    Utf32,
+}
 
-    for (idx, c) in source.chars().enumerate() {
-        if idx >= byte_pos {
-            break;
+impl PositionEncoding {
+    pub fn from_kind(kind: &PositionEncodingKind) -> Option<PositionEncoding> {
+        match kind.as_str() {
+            "utf-8" => Some(PositionEncoding::Utf8),
+            "utf-16" => Some(PositionEncoding::Utf16),
+            "utf-32" => Some(PositionEncoding::Utf32),
+            _ => None,
         }
-        if c == '\n' {
-            line += 1;
-            col = 0;
-        } else {
-            col += 1;
+    }
+
+    pub fn kind(&self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
         }
     }
 
-    Position {
-        line,
-        character: col.saturating_sub(1),
+    fn char_len(&self, c: char) -> usize {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+}
+
+/// The byte offset where `line` starts and its text, without the line ending
+pub fn get_line(source: &str, line: usize) -> Option<(usize, &str)> {
+    let mut line_start = 0;
+    for _ in 0..line {
+        line_start += source[line_start..].find('\n')? + 1;
     }
+
+    let line_text = &source[line_start..];
+    let line_text = match line_text.find('\n') {
+        Some(end) => line_text[..end]
+            .strip_suffix('\r')
+            .unwrap_or(&line_text[..end]),
+        None => line_text,
+    };
+
+    Some((line_start, line_text))
 }
 
-pub fn get_text_byte_offset(source: &str, line: usize, character: usize) -> Option<usize> {
-    let mut character = character;
-    let mut line = line;
-    let mut byte_offset = 0;
+/// Converts a byte column of `line_text` into a character in `encoding`
+pub fn byte_col_to_character(line_text: &str, byte_col: usize, encoding: PositionEncoding) -> u32 {
+    line_text
+        .char_indices()
+        .take_while(|(idx, _)| *idx < byte_col)
+        .map(|(_, c)| encoding.char_len(c))
+        .sum::<usize>() as u32
+}
 
-    for l in source.lines() {
-        if line == 0 {
-            byte_offset += character;
-            character = character.saturating_sub(l.len());
-            break;
-        } else {
-            line -= 1;
-            byte_offset += l.len() + 1;
+/// Converts a character in `encoding` into a byte column of `line_text`.
+/// Characters past the end of the line are clamped to the line end as the
+/// spec demands.
+pub fn character_to_byte_col(
+    line_text: &str,
+    character: usize,
+    encoding: PositionEncoding,
+) -> usize {
+    let mut col = 0;
+    for (idx, c) in line_text.char_indices() {
+        if col >= character {
+            return idx;
         }
+        col += encoding.char_len(c);
+    }
+
+    line_text.len()
+}
+
+pub fn byte_pos_to_line_col(source: &str, byte_pos: usize, encoding: PositionEncoding) -> Position {
+    let before = &source[..byte_pos.min(source.len())];
+    let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: byte_col_to_character(&before[line_start..], usize::MAX, encoding),
+    }
+}
+
+/// Converts an LSP line/character pair into a byte offset. Lines past the end
+/// of the document return None.
+pub fn get_text_byte_offset(
+    source: &str,
+    line: usize,
+    character: usize,
+    encoding: PositionEncoding,
+) -> Option<usize> {
+    let (line_start, line_text) = get_line(source, line)?;
+
+    Some(line_start + character_to_byte_col(line_text, character, encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{byte_pos_to_line_col, get_text_byte_offset, PositionEncoding};
+    use lsp_types::Position;
+
+    const SOURCE: &str = "<p>\r\n  Grüße 👋 <b>x</b>\n</p>";
+
+    #[test]
+    fn test_byte_pos_to_line_col_in_every_encoding() {
+        let byte_pos = SOURCE.find("<b>").expect("to exist");
+
+        assert_eq!(
+            byte_pos_to_line_col(SOURCE, byte_pos, PositionEncoding::Utf8),
+            Position::new(1, 15)
+        );
+        assert_eq!(
+            byte_pos_to_line_col(SOURCE, byte_pos, PositionEncoding::Utf16),
+            Position::new(1, 11)
+        );
+        assert_eq!(
+            byte_pos_to_line_col(SOURCE, byte_pos, PositionEncoding::Utf32),
+            Position::new(1, 10)
+        );
     }
 
-    if line != 0 || character != 0 {
-        return None;
+    #[test]
+    fn test_get_text_byte_offset_in_every_encoding() {
+        let byte_pos = SOURCE.find("<b>");
+
+        assert_eq!(
+            get_text_byte_offset(SOURCE, 1, 15, PositionEncoding::Utf8),
+            byte_pos
+        );
+        assert_eq!(
+            get_text_byte_offset(SOURCE, 1, 11, PositionEncoding::Utf16),
+            byte_pos
+        );
+        assert_eq!(
+            get_text_byte_offset(SOURCE, 1, 10, PositionEncoding::Utf32),
+            byte_pos
+        );
     }
 
-    Some(byte_offset)
+    #[test]
+    fn test_get_text_byte_offset_clamps_to_the_line() {
+        assert_eq!(
+            get_text_byte_offset(SOURCE, 0, 40, PositionEncoding::Utf16),
+            Some(3)
+        );
+        assert_eq!(
+            get_text_byte_offset(SOURCE, 2, 4, PositionEncoding::Utf16),
+            SOURCE.find("</p>").map(|i| i + 4)
+        );
+        assert_eq!(
+            get_text_byte_offset(SOURCE, 3, 0, PositionEncoding::Utf16),
+            None
+        );
+    }
 }